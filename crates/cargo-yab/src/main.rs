@@ -0,0 +1,319 @@
+//! Cargo subcommand running `yab` benchmarks without the need for the `--` separator.
+
+use std::{env, ffi::OsString, fs, io, path::PathBuf, process, process::Command};
+
+const USAGE: &str = "\
+Runs yab benchmarks via `cargo bench`
+
+Usage: cargo yab [BENCH] [ARGS]... [-- [BENCH_ARGS]...]
+
+Arguments:
+  [BENCH]          Name of the bench target. If omitted (e.g., the first arg is an option), args
+                   are forwarded to all bench targets
+  [ARGS]...        Args forwarded to the benchmark executable (e.g., `--vs pub:main`), except for
+                   the Cargo options listed below, which are forwarded to `cargo bench`
+  [BENCH_ARGS]...  Args forwarded to the benchmark executable as-is
+
+Cargo options:
+  -p, --package <SPEC>, --exclude <SPEC>, --workspace, -F, --features <FEATURES>,
+  --all-features, --no-default-features, --profile <NAME>, --target <TRIPLE>,
+  --target-dir <DIR>, --manifest-path <PATH>, --locked, --offline, --frozen, --no-fail-fast
+
+Exits with code 2 if benchmark filters match no benchmarks in all bench targets.";
+
+/// Env var specifying the file for benchmark executables to record whether benchmark filters have matched
+/// any benchmarks to.
+const MATCHES_FILE_VAR: &str = "YAB_MATCHES_FILE";
+/// Exit code for invalid usage (e.g., unmatched benchmark filters); same as in `yab`.
+const USAGE_CODE: i32 = 2;
+
+/// Cargo options taking a value that are forwarded to `cargo bench` rather than to the benchmark executable.
+/// Options that conflict with benchmark options (e.g., `--jobs`, `--color` or `--quiet`) are intentionally
+/// not recognized.
+const CARGO_OPTIONS: &[&str] = &[
+    "-p",
+    "--package",
+    "--exclude",
+    "-F",
+    "--features",
+    "--profile",
+    "--target",
+    "--target-dir",
+    "--manifest-path",
+];
+
+/// Cargo flags (i.e., options without a value) forwarded to `cargo bench`.
+const CARGO_FLAGS: &[&str] = &[
+    "--workspace",
+    "--all-features",
+    "--no-default-features",
+    "--locked",
+    "--offline",
+    "--frozen",
+    "--no-fail-fast",
+];
+
+/// Subcommand args split between Cargo and the benchmark executable.
+#[derive(Debug, Default)]
+struct SplitArgs {
+    cargo: Vec<OsString>,
+    bench: Option<OsString>,
+    executable: Vec<OsString>,
+}
+
+impl SplitArgs {
+    fn new(args: Vec<OsString>) -> Self {
+        let mut this = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let arg_str = arg.to_string_lossy();
+            if arg_str == "--" {
+                break;
+            } else if CARGO_FLAGS.contains(&&*arg_str) {
+                this.cargo.push(arg);
+            } else if CARGO_OPTIONS.contains(&&*arg_str) {
+                this.cargo.push(arg);
+                this.cargo.extend(args.next());
+            } else if Self::is_cargo_option_with_value(&arg_str) {
+                this.cargo.push(arg);
+            } else {
+                this.executable.push(arg);
+            }
+        }
+
+        if this
+            .executable
+            .first()
+            .is_some_and(|arg| !arg.to_string_lossy().starts_with('-'))
+        {
+            this.bench = Some(this.executable.remove(0));
+        }
+        // Args after `--` are forwarded as-is.
+        this.executable.extend(args);
+        this
+    }
+
+    /// Checks whether the arg is a Cargo option with an inline value, e.g. `--features=foo` or `-pfoo`.
+    fn is_cargo_option_with_value(arg: &str) -> bool {
+        CARGO_OPTIONS.iter().any(|&option| {
+            if option.starts_with("--") {
+                arg.strip_prefix(option)
+                    .is_some_and(|rest| rest.starts_with('='))
+            } else {
+                arg.len() > option.len() && arg.starts_with(option)
+            }
+        })
+    }
+}
+
+/// Private temporary directory for the matches file. Removed on drop.
+#[derive(Debug)]
+struct MatchesDir(PathBuf);
+
+impl MatchesDir {
+    fn new() -> io::Result<Self> {
+        let path = env::temp_dir().join(format!("cargo-yab-{}", process::id()));
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        // Fails if the directory already exists, so that it cannot be hijacked.
+        builder.create(&path)?;
+        Ok(Self(path))
+    }
+
+    fn file_path(&self) -> PathBuf {
+        self.0.join("matches")
+    }
+}
+
+impl Drop for MatchesDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+/// Returns the error message if benchmark filters haven't matched any benchmarks in all bench targets.
+/// `records` are lines recorded by benchmark executables to the matches file.
+fn unmatched_filters_error(records: &str) -> Option<&str> {
+    let mut error = None;
+    for line in records.lines() {
+        if line == "matched" {
+            return None;
+        } else if let Some(message) = line.strip_prefix("unmatched: ") {
+            error.get_or_insert(message);
+        }
+    }
+    error
+}
+
+/// Translates subcommand args into the `cargo bench` command.
+fn bench_command(cargo: OsString, args: Vec<OsString>) -> Command {
+    let args = SplitArgs::new(args);
+    let mut command = Command::new(cargo);
+    command.arg("bench").args(args.cargo);
+    if let Some(bench) = args.bench {
+        command.arg("--bench").arg(bench);
+    }
+    command.arg("--").args(args.executable);
+    command
+}
+
+fn main() {
+    let mut args: Vec<_> = env::args_os().skip(1).collect();
+    // When invoked as `cargo yab ...`, Cargo passes the subcommand name as the first arg.
+    if args.first().is_some_and(|arg| arg == "yab") {
+        args.remove(0);
+    }
+    if args
+        .first()
+        .is_some_and(|arg| arg == "--help" || arg == "-h")
+    {
+        println!("{USAGE}");
+        return;
+    }
+
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let mut command = bench_command(cargo, args);
+    // Benchmark executables record filter matches instead of failing, so that unmatched filters are only an error
+    // if they match no benchmarks in all bench targets. If the directory cannot be created, executables
+    // check matches on their own.
+    let matches_dir = MatchesDir::new()
+        .map_err(|err| {
+            eprintln!("Failed creating temporary directory to check filter matches: {err}")
+        })
+        .ok();
+    if let Some(dir) = &matches_dir {
+        command.env(MATCHES_FILE_VAR, dir.file_path());
+    }
+
+    let status = match command.status() {
+        Ok(status) => status,
+        Err(err) => {
+            eprintln!("Failed running {command:?}: {err}");
+            process::exit(1);
+        }
+    };
+    let mut exit_code = status.code().unwrap_or(1);
+    if let (true, Some(dir)) = (status.success(), &matches_dir) {
+        let records = fs::read_to_string(dir.file_path()).unwrap_or_default();
+        if let Some(message) = unmatched_filters_error(&records) {
+            eprintln!("error: {message}");
+            exit_code = USAGE_CODE;
+        }
+    }
+    // `process::exit()` doesn't run destructors.
+    drop(matches_dir);
+    process::exit(exit_code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command_args(command: &Command) -> Vec<&str> {
+        command
+            .get_args()
+            .map(|arg| arg.to_str().unwrap())
+            .collect()
+    }
+
+    fn os_args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn translating_args() {
+        let args = os_args(&["all", "--vs", "pub:main", "fib"]);
+        let command = bench_command("cargo".into(), args);
+        assert_eq!(command.get_program(), "cargo");
+        assert_eq!(
+            command_args(&command),
+            ["bench", "--bench", "all", "--", "--vs", "pub:main", "fib"]
+        );
+
+        let command = bench_command("cargo".into(), os_args(&["--list"]));
+        assert_eq!(command_args(&command), ["bench", "--", "--list"]);
+
+        let command = bench_command("/usr/bin/cargo".into(), vec![]);
+        assert_eq!(command.get_program(), "/usr/bin/cargo");
+        assert_eq!(command_args(&command), ["bench", "--"]);
+    }
+
+    #[test]
+    fn splitting_cargo_args() {
+        let args = os_args(&[
+            "-p",
+            "yab-e2e-tests",
+            "all",
+            "--features=serde",
+            "--vs",
+            "pub:main",
+            "--locked",
+            "-Finstrumentation",
+            "--jobs=2",
+        ]);
+        let command = bench_command("cargo".into(), args);
+        assert_eq!(
+            command_args(&command),
+            [
+                "bench",
+                "-p",
+                "yab-e2e-tests",
+                "--features=serde",
+                "--locked",
+                "-Finstrumentation",
+                "--bench",
+                "all",
+                "--",
+                "--vs",
+                "pub:main",
+                "--jobs=2"
+            ]
+        );
+
+        let args = os_args(&["--profile", "bench", "--list"]);
+        let command = bench_command("cargo".into(), args);
+        assert_eq!(
+            command_args(&command),
+            ["bench", "--profile", "bench", "--", "--list"]
+        );
+    }
+
+    #[test]
+    fn forwarding_args_after_separator() {
+        let args = os_args(&["all", "--workspace", "--", "--features", "fib"]);
+        let command = bench_command("cargo".into(), args);
+        assert_eq!(
+            command_args(&command),
+            [
+                "bench",
+                "--workspace",
+                "--bench",
+                "all",
+                "--",
+                "--features",
+                "fib"
+            ]
+        );
+
+        // The first arg after `--` is not treated as a bench target.
+        let command = bench_command("cargo".into(), os_args(&["--", "fib"]));
+        assert_eq!(command_args(&command), ["bench", "--", "fib"]);
+    }
+
+    #[test]
+    fn checking_filter_matches() {
+        // No benchmark executables were run.
+        assert_eq!(unmatched_filters_error(""), None);
+
+        let records = "unmatched: No benchmarks match the filter `fibb`\n\
+                       unmatched: No benchmarks match the filter `fibb`\n";
+        assert_eq!(
+            unmatched_filters_error(records),
+            Some("No benchmarks match the filter `fibb`")
+        );
+
+        let records = "unmatched: No benchmarks match the filter `fib`\nmatched\n";
+        assert_eq!(unmatched_filters_error(records), None);
+    }
+}
@@ -1,14 +1,36 @@
 //! [`Bencher`] and tightly related types.
 
-use std::{env, fs, mem, panic, process, sync::Arc, thread, thread::JoinHandle};
+use std::{
+    cell::{Cell, OnceCell},
+    env, fmt, fs,
+    future::Future,
+    io::{self, Write},
+    mem, panic,
+    path::PathBuf,
+    process,
+    process::Command,
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    thread::JoinHandle,
+    time::Instant,
+};
 
+#[cfg(feature = "http")]
+use crate::reporter::HttpReporter;
 use crate::{
     cachegrind,
-    cachegrind::SpawnArgs,
-    options::{BenchOptions, CachegrindOptions, IdMatcher, Options},
-    reporter::{BenchmarkOutput, BenchmarkReporter, PrintingReporter, Reporter, SeqReporter},
-    utils::Semaphore,
-    BenchmarkId, CachegrindStats, Capture,
+    cachegrind::{CachegrindError, ExternalCommand, SpawnArgs, Tool},
+    history::{Comparison, History},
+    options::{
+        absolute_path, BenchOptions, CachegrindOptions, FileMatcher, IdMatcher, KeepOutput,
+        Options, SortOrder, TagMatcher,
+    },
+    reporter::{
+        BenchmarkOutput, BenchmarkProcessor, BenchmarkReporter, CyclesEstimator, HtmlReporter,
+        JunitReporter, PrintingReporter, ProcessingReporter, Reporter, SeqReporter,
+    },
+    utils::{iterations_str, Semaphore, TempDir},
+    AccessSummary, BenchmarkId, CachegrindStats, Capture, IncompatibleStatsError,
 };
 
 /// Mode in which the bencher is currently executing.
@@ -25,6 +47,370 @@ pub enum BenchMode {
     PrintResults,
 }
 
+/// Configuration for a single benchmark that overrides global command-line options.
+///
+/// # Examples
+///
+/// ```
+/// use yab::{black_box, BenchConfig, Bencher};
+///
+/// fn benchmarks(bencher: &mut Bencher) {
+///     let config = BenchConfig::default().warm_up(5_000_000);
+///     bencher.bench_with_config("sum", config, |capture| {
+///         let values: Vec<u64> = (0..1_000).collect();
+///         capture.measure(|| black_box(&values).iter().sum::<u64>());
+///     });
+/// }
+/// # yab::main!(benchmarks);
+/// ```
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct BenchConfig {
+    /// Target number of instructions for the benchmark warm-up. If set, overrides the `--warm-up`
+    /// command-line option for the benchmark. The number of iterations is still bounded
+    /// by `--max-iterations` (or [`Self::max_iterations()`] if set).
+    pub warm_up_instructions: Option<u64>,
+    /// Maximum number of warm-up iterations. If set, overrides the `--max-iterations` command-line option
+    /// for the benchmark.
+    pub max_iterations: Option<u64>,
+    /// Maximum number of captured instructions. If the benchmark captures more instructions, it fails
+    /// regardless of the previous stats.
+    pub instruction_budget: Option<u64>,
+}
+
+impl BenchConfig {
+    /// Sets the target number of instructions for the benchmark warm-up.
+    #[must_use]
+    pub fn warm_up(mut self, instructions: u64) -> Self {
+        self.warm_up_instructions = Some(instructions);
+        self
+    }
+
+    /// Sets the maximum number of warm-up iterations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `iterations` is zero.
+    #[must_use]
+    pub fn max_iterations(mut self, iterations: u64) -> Self {
+        assert!(iterations > 0, "`max_iterations` must be positive");
+        self.max_iterations = Some(iterations);
+        self
+    }
+
+    /// Sets the maximum number of captured instructions. The benchmark will fail if it exceeds the budget,
+    /// independently of the previous stats. The budget isn't checked for approximate stats collected
+    /// with `--fast`, since they include setup.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use yab::{black_box, BenchConfig, Bencher};
+    ///
+    /// fn benchmarks(bencher: &mut Bencher) {
+    ///     let config = BenchConfig::default().budget_instructions(2_000);
+    ///     bencher.bench_with_config("fib_short", config, |capture| {
+    ///         capture.measure(|| (0..black_box(10_u64)).product::<u64>());
+    ///     });
+    /// }
+    /// # yab::main!(benchmarks);
+    /// ```
+    #[must_use]
+    pub fn budget_instructions(mut self, budget: u64) -> Self {
+        self.instruction_budget = Some(budget);
+        self
+    }
+}
+
+/// Setup shared among benchmarks, which is lazily performed at most once per process.
+/// Created via [`Bencher::global_setup()`].
+///
+/// Each `cachegrind` run of a benchmark (calibration, baseline and full runs) is performed in a separate process,
+/// so the setup cannot be shared among these runs; it will be performed once in each spawned process that accesses
+/// the setup value. Still, this saves performing the setup on each benchmark iteration, or for each benchmark
+/// in the test mode.
+///
+/// The setup is performed on the first call to [`Self::get()`]. Make sure that this call happens outside
+/// the captured region; otherwise, the setup may be captured if the benchmark runs a single iteration
+/// (e.g., with `--warm-up=0`).
+pub struct GlobalSetup<T, F = fn() -> T> {
+    value: OnceCell<T>,
+    setup: Cell<Option<F>>,
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for GlobalSetup<T, F> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("GlobalSetup")
+            .field("value", &self.value.get())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, F: FnOnce() -> T> GlobalSetup<T, F> {
+    /// Returns the setup value, performing the setup if necessary.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the setup has panicked previously, or if this method is called during the setup.
+    pub fn get(&self) -> &T {
+        self.value.get_or_init(|| {
+            let setup = self
+                .setup
+                .take()
+                .expect("setup has panicked or is reentrant");
+            setup()
+        })
+    }
+}
+
+/// Fatal error that has occurred when running benchmarks via [`Bencher::try_run()`].
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum BenchError {
+    /// Error running `cachegrind` or processing its outputs, e.g., `cachegrind` not being installed,
+    /// exiting abnormally, or producing an unparseable output.
+    #[error(transparent)]
+    Cachegrind(#[from] CachegrindError),
+    /// I/O error moving a `cachegrind` output to its final location.
+    #[error("I/O error moving cachegrind output `{from}` to `{to}`: {error}")]
+    MoveOutput {
+        /// Path to the temporary output.
+        from: String,
+        /// Final path to the output.
+        to: String,
+        /// Underlying I/O error.
+        #[source]
+        error: io::Error,
+    },
+    /// Baseline and full stats for a benchmark were collected by different collectors.
+    #[error(transparent)]
+    IncompatibleStats(#[from] IncompatibleStatsError),
+    /// Benchmark has captured zero instructions, and `--fail-on-zero` is specified.
+    #[error(
+        "benchmark has captured zero instructions; make sure that the benchmarked code isn't empty \
+         and that the capture is started around it"
+    )]
+    ZeroInstructions,
+    /// Benchmark has captured more instructions than its budget set via [`BenchConfig::budget_instructions()`].
+    #[error("{}", budget_message(*.budget, *.actual))]
+    BudgetExceeded {
+        /// Instruction budget for the benchmark.
+        budget: u64,
+        /// Number of captured instructions.
+        actual: u64,
+    },
+    /// Benchmark filter (FILTER in the command-line args) hasn't matched any benchmarks, and `--allow-no-matches`
+    /// is not specified.
+    #[error("{}", no_matches_message(.filter, *.exact))]
+    NoMatches {
+        /// Filter specified in the command-line args.
+        filter: String,
+        /// Whether the filter is exact (i.e., `--exact` is specified).
+        exact: bool,
+    },
+    /// None of benchmarks matching the benchmark filter (if any) have the tags specified via `--filter-tag`,
+    /// and `--allow-no-matches` is not specified.
+    #[error("{}", no_tag_matches_message(.0))]
+    NoTagMatches(Vec<(String, String)>),
+    /// Some benchmarks have failed. The errors are ordered by benchmark completion. Only the failed benchmark IDs
+    /// are listed in the error message since each error is reported when the corresponding benchmark fails.
+    #[error("There were benchmark failures: {}", failures_message(.0))]
+    BenchmarkFailures(Vec<(BenchmarkId, BenchError)>),
+    /// A benchmarking job has panicked, e.g., in a [`BenchmarkProcessor`].
+    #[error("At least one of benchmarking jobs failed")]
+    JobPanic,
+    /// Some tests have failed.
+    #[error("There were test failures")]
+    TestFailures,
+    /// I/O error listing benchmarks.
+    #[error("Failed listing benchmarks: {0}")]
+    List(#[source] io::Error),
+    /// I/O error writing a report, e.g. the HTML report specified via `--html`.
+    #[error("Failed writing report to `{path}`: {error}")]
+    Report {
+        /// Path to the report file.
+        path: String,
+        /// Underlying I/O error.
+        #[source]
+        error: io::Error,
+    },
+}
+
+impl BenchError {
+    /// Exit code for benchmark failures, e.g. if `cachegrind` cannot be executed or its output cannot be parsed.
+    pub const BENCHMARK_FAILURE_CODE: i32 = 1;
+    /// Exit code for invalid command-line usage, e.g. invalid options or a benchmark filter not matching
+    /// any benchmarks. Consistent with the exit code for unparseable command-line args.
+    pub const USAGE_CODE: i32 = 2;
+    /// Exit code for test failures (i.e., benchmarks panicking in the test mode).
+    pub const TEST_FAILURE_CODE: i32 = 3;
+
+    /// Returns the process exit code corresponding to this error. Benchmark executables created with
+    /// [`main!`](crate::main) exit with this code, so that scripts can branch on it.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::NoMatches { .. } | Self::NoTagMatches(_) => Self::USAGE_CODE,
+            Self::TestFailures => Self::TEST_FAILURE_CODE,
+            _ => Self::BENCHMARK_FAILURE_CODE,
+        }
+    }
+}
+
+#[allow(clippy::cast_precision_loss)] // fine for reporting
+fn budget_message(budget: u64, actual: u64) -> String {
+    let overage = actual - budget;
+    let percentage = overage as f64 * 100.0 / budget.max(1) as f64;
+    format!(
+        "benchmark has captured {actual} instructions, exceeding its budget of {budget} instructions \
+         by {overage} (+{percentage:.2}%)"
+    )
+}
+
+fn no_matches_message(filter: &str, exact: bool) -> String {
+    if exact {
+        let mut message = format!("No benchmarks match the exact filter `{filter}`");
+        if has_regex_operators(filter) {
+            message.push_str(
+                "; the filter contains regex metacharacters, consider dropping `--exact` to interpret it as a regex",
+            );
+        }
+        message
+    } else {
+        format!("No benchmarks match the filter `{filter}`")
+    }
+}
+
+/// Checks whether the filter contains regex operators. Unlike [`regex::escape()`], this doesn't consider chars
+/// only escaped for verbose mode or character classes (e.g., `-` or `#`) since they are common in benchmark names.
+fn has_regex_operators(filter: &str) -> bool {
+    const OPERATORS: &[char] = &[
+        '.', '^', '$', '*', '+', '?', '(', ')', '[', ']', '{', '}', '|', '\\',
+    ];
+    filter.contains(OPERATORS)
+}
+
+fn no_tag_matches_message(tags: &[(String, String)]) -> String {
+    let tags = tags.iter().map(|(key, value)| format!("`{key}={value}`"));
+    let tags = tags.collect::<Vec<_>>().join(", ");
+    format!("No benchmarks have the tags specified via `--filter-tag`: {tags}")
+}
+
+fn failures_message(failures: &[(BenchmarkId, BenchError)]) -> String {
+    let ids = failures.iter().map(|(id, _)| format!("`{id}`"));
+    ids.collect::<Vec<_>>().join(", ")
+}
+
+/// Benchmark failures shared among benchmarking jobs.
+type SharedFailures = Arc<Mutex<Vec<(BenchmarkId, BenchError)>>>;
+
+fn has_failures(failures: &SharedFailures) -> bool {
+    !failures
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .is_empty()
+}
+
+/// Benchmarked code.
+#[derive(Debug, Clone)]
+enum BenchTarget {
+    /// Function in this executable, which is spawned under `cachegrind` with instrumentation args.
+    Function,
+    /// External command spawned under `cachegrind` directly.
+    Command(ExternalCommand),
+}
+
+/// Benchmarks collected with `--collect-first`.
+type CollectedBenchmarks = Vec<(BenchmarkId, BenchConfig, BenchTarget)>;
+
+/// Benchmarking jobs spawned in the benchmarking mode.
+#[derive(Debug)]
+struct BenchJobs {
+    this_executable: String,
+    fail_fast: bool,
+    semaphore: Arc<Semaphore>,
+    handles: Vec<JoinHandle<()>>,
+    /// Filled with fatal benchmark errors.
+    failures: SharedFailures,
+}
+
+impl BenchJobs {
+    fn new(options: &BenchOptions, this_executable: String) -> Self {
+        Self {
+            this_executable,
+            fail_fast: options.fail_fast,
+            semaphore: Arc::new(Semaphore::new(options.jobs.get())),
+            handles: vec![],
+            failures: SharedFailures::default(),
+        }
+    }
+
+    /// Runs the benchmark for all cache geometry variants. Runs for the same benchmark are grouped together.
+    fn run(
+        &mut self,
+        options: &BenchOptions,
+        reporter: &mut SeqReporter,
+        (id, config, target): (BenchmarkId, BenchConfig, BenchTarget),
+    ) {
+        for (label, options) in options.geometry_variants() {
+            let run_id = variant_id(&id, label.as_deref());
+            let runner = CachegrindRunner {
+                options,
+                config: config.clone(),
+                target: target.clone(),
+                this_executable: self.this_executable.clone(),
+                reporter: reporter.new_benchmark(&run_id),
+                id: run_id,
+                spawn_id: id.clone(),
+                failures: self.failures.clone(),
+            };
+            self.spawn(runner);
+        }
+    }
+
+    fn spawn(&mut self, runner: CachegrindRunner) {
+        let fail_fast = self.fail_fast;
+        if fail_fast && has_failures(&self.failures) {
+            runner.skip();
+        } else if self.semaphore.capacity() == 1 {
+            // Run the runner synchronously in order to have deterministic ordering
+            runner.run_benchmark();
+        } else {
+            let semaphore = self.semaphore.clone();
+            let failures = self.failures.clone();
+            self.handles.push(thread::spawn(move || {
+                let _permit = semaphore.acquire_owned();
+                // The job may have been waiting for a permit while another job has failed.
+                if fail_fast && has_failures(&failures) {
+                    runner.skip();
+                } else {
+                    runner.run_benchmark();
+                }
+            }));
+        }
+    }
+
+    /// Waits for all spawned jobs to complete. Returns an error if any benchmark has failed.
+    fn join(&mut self) -> Result<(), BenchError> {
+        let mut has_panicked = false;
+        for handle in mem::take(&mut self.handles) {
+            has_panicked |= handle.join().is_err();
+        }
+        if has_panicked {
+            // E.g., a benchmark processor has panicked
+            return Err(BenchError::JobPanic);
+        }
+
+        let failures =
+            mem::take(&mut *self.failures.lock().unwrap_or_else(PoisonError::into_inner));
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(BenchError::BenchmarkFailures(failures))
+        }
+    }
+}
+
 /// Mode-specific data.
 #[derive(Debug)]
 enum BenchModeData {
@@ -32,34 +418,48 @@ enum BenchModeData {
         should_fail: bool,
     },
     Bench {
+        jobs: BenchJobs,
+        /// Benchmarks collected with `--collect-first`. They are run once all of them are registered,
+        /// so that the total number of benchmarks is known beforehand.
+        collected: Option<CollectedBenchmarks>,
+    },
+    List {
+        ids: Vec<BenchmarkId>,
+    },
+    /// Benchmarking mode in which `cachegrind` commands are printed instead of being executed.
+    DryRun {
         this_executable: String,
-        jobs_semaphore: Arc<Semaphore>,
-        jobs: Vec<JoinHandle<()>>,
     },
-    List,
-    PrintResults,
+    PrintResults {
+        /// Benchmarks collected with `--collect-first`.
+        collected: Option<CollectedBenchmarks>,
+    },
 }
 
 impl BenchModeData {
-    fn new(options: &BenchOptions) -> Self {
+    fn new(options: &BenchOptions, this_executable: &str) -> Self {
         match options.mode() {
             BenchMode::Test => Self::Test { should_fail: false },
+            BenchMode::Bench if options.dry_run => Self::DryRun {
+                this_executable: options.resolve_path(this_executable),
+            },
             BenchMode::Bench => Self::Bench {
-                this_executable: env::args().next().expect("no executable arg"),
-                jobs_semaphore: Arc::new(Semaphore::new(options.jobs.get())),
-                jobs: vec![],
+                jobs: BenchJobs::new(options, options.resolve_path(this_executable)),
+                collected: options.collect_first.then(Vec::new),
+            },
+            BenchMode::List => Self::List { ids: vec![] },
+            BenchMode::PrintResults => Self::PrintResults {
+                collected: options.collect_first.then(Vec::new),
             },
-            BenchMode::List => Self::List,
-            BenchMode::PrintResults => Self::PrintResults,
         }
     }
 
     fn mode(&self) -> BenchMode {
         match self {
             Self::Test { .. } => BenchMode::Test,
-            Self::Bench { .. } => BenchMode::Bench,
-            Self::List => BenchMode::List,
-            Self::PrintResults => BenchMode::PrintResults,
+            Self::Bench { .. } | Self::DryRun { .. } => BenchMode::Bench,
+            Self::List { .. } => BenchMode::List,
+            Self::PrintResults { .. } => BenchMode::PrintResults,
         }
     }
 }
@@ -69,73 +469,301 @@ impl BenchModeData {
 struct MainBencher {
     options: BenchOptions,
     id_matcher: IdMatcher,
+    tag_matcher: TagMatcher,
+    file_matcher: FileMatcher,
     mode: BenchModeData,
     reporter: SeqReporter,
+    cycles_estimator: CyclesEstimator,
+    /// Temporary directory for raw outputs with `--compare-only`. Removed once all benchmarks are completed.
+    compare_only_dir: Option<TempDir>,
+    /// HTML reporter (also included into `reporter`). The report is written explicitly in [`Self::finish()`]
+    /// so that I/O errors are surfaced as a [`BenchError`].
+    html_reporter: Option<HtmlReporter>,
+    /// Set if FILTER has matched at least one benchmark.
+    has_id_matches: bool,
+    /// Set if FILTER and `--filter-tag` have matched at least one benchmark.
+    has_tag_matches: bool,
+    /// Set once the benchmarks are completed, either explicitly or on drop.
+    is_finished: bool,
 }
 
 impl Drop for MainBencher {
     fn drop(&mut self) {
-        if thread::panicking() {
+        if thread::panicking() || self.is_finished {
             return;
         }
-
-        match &mut self.mode {
-            BenchModeData::Bench { jobs, .. } => {
-                for job in mem::take(jobs) {
-                    if job.join().is_err() {
-                        self.reporter
-                            .error(&"At least one of benchmarking jobs failed");
-                        break;
-                    }
-                }
-            }
-            BenchModeData::Test { should_fail } if *should_fail => {
-                self.reporter.error(&"There were test failures");
-                process::exit(1);
-            }
-            _ => { /* no special handling required */ }
+        if let Err(err) = self.finish() {
+            // The error is already reported
+            process::exit(err.exit_code());
         }
-        mem::take(&mut self.reporter).ok_all();
     }
 }
 
 impl MainBencher {
-    fn new(options: BenchOptions) -> Self {
+    fn new(mut options: BenchOptions, this_executable: &str) -> Self {
         let mut reporter = PrintingReporter::new(options.styling(), options.verbosity());
         if !options.validate(&mut reporter) {
-            process::exit(1);
+            process::exit(BenchError::USAGE_CODE);
         }
-        let mode = BenchModeData::new(&options);
+        let compare_only_dir = Self::create_compare_only_dir(&mut options, &reporter);
+        let mode = BenchModeData::new(&options, this_executable);
+        // Dry runs don't require `cachegrind`
         if matches!(mode, BenchModeData::Bench { .. }) {
             match cachegrind::check() {
                 Ok(version) => {
                     reporter.report_debug(format_args!("Using cachegrind with version {version}"));
+                    options.check_cachegrind_version(version, &mut reporter);
+                }
+                Err(err @ CachegrindError::Version(_)) => {
+                    // The version is only used for non-critical checks, so it's not worth failing benchmarks over.
+                    let warning = format!("{err}; skipping cachegrind version checks");
+                    reporter.report_warning(None, &warning);
                 }
                 Err(err) => {
+                    let err = BenchError::from(err);
                     reporter.report_error(None, &err);
-                    process::exit(1);
+                    process::exit(err.exit_code());
                 }
             }
+            options.check_aslr(&mut reporter);
         }
 
         let id_matcher = match options.id_matcher() {
             Ok(matcher) => matcher,
             Err(err) => {
                 reporter.report_error(None, &err);
-                process::exit(1);
+                process::exit(BenchError::USAGE_CODE);
             }
         };
+        let tag_matcher = options.tag_matcher();
+        let file_matcher = options.file_matcher();
+
+        if options.sort == SortOrder::Name {
+            reporter.defer_outputs();
+        }
+        reporter.set_noise_threshold(options.noise_threshold / 100.0);
+        if options.changed_only {
+            reporter.print_changed_only();
+        }
+        if options.show_contributions {
+            reporter.show_contributions();
+        }
+        let cycles_estimator = reporter.cycles_estimator();
+        #[cfg(feature = "http")]
+        let printer = reporter.clone();
+        let mut reporters: Vec<Box<dyn Reporter>> = vec![Box::new(reporter)];
+        if let (BenchModeData::Test { .. }, Some(junit_path)) = (&mode, options.junit_path()) {
+            reporters.push(Box::new(JunitReporter::new(junit_path)));
+        }
+        let mut html_reporter = None;
+        if let (BenchModeData::Bench { .. } | BenchModeData::PrintResults { .. }, Some(html_path)) =
+            (&mode, options.html_path())
+        {
+            let noise_threshold = options.noise_threshold / 100.0;
+            let reporter = HtmlReporter::new(html_path, cycles_estimator.clone(), noise_threshold);
+            reporters.push(Box::new(reporter.clone()));
+            html_reporter = Some(reporter);
+        }
+        #[cfg(feature = "http")]
+        if let (BenchModeData::Bench { .. } | BenchModeData::PrintResults { .. }, Some(url)) =
+            (&mode, &options.report_url)
+        {
+            let reporter = HttpReporter::new(url.clone(), options.report_token.clone(), printer);
+            reporters.push(Box::new(reporter));
+        }
 
         Self {
             options,
             id_matcher,
+            tag_matcher,
+            file_matcher,
             mode,
-            reporter: SeqReporter(vec![Box::new(reporter)]),
+            reporter: SeqReporter(reporters),
+            cycles_estimator,
+            has_id_matches: false,
+            has_tag_matches: false,
+            compare_only_dir,
+            html_reporter,
+            is_finished: false,
+        }
+    }
+
+    /// Creates a temporary directory for raw outputs if `--compare-only` is specified. The directory has
+    /// an unpredictable name, and is owned by the bencher for the entire benchmarking run.
+    fn create_compare_only_dir(
+        options: &mut BenchOptions,
+        reporter: &PrintingReporter,
+    ) -> Option<TempDir> {
+        if !options.compare_only || options.mode() != BenchMode::Bench {
+            return None;
+        }
+        match TempDir::new("yab-") {
+            Ok(dir) => {
+                options.compare_only_dir = Some(dir.path().to_string_lossy().into_owned());
+                Some(dir)
+            }
+            Err(err) => {
+                let err = format!("Failed creating temporary directory for raw outputs: {err}");
+                reporter.report_error(None, &err);
+                process::exit(BenchError::BENCHMARK_FAILURE_CODE);
+            }
+        }
+    }
+
+    /// Completes running or otherwise processing benchmarks depending on the mode. Errors are reported
+    /// before being returned.
+    fn finish(&mut self) -> Result<(), BenchError> {
+        self.is_finished = true;
+        if let Err(err) = self.check_matches() {
+            self.reporter.error(&err);
+            return Err(err);
+        }
+
+        match &mut self.mode {
+            BenchModeData::Bench { jobs, collected } => {
+                if let Some(benchmarks) = collected.take() {
+                    let variant_count = self.options.geometry_variants().len();
+                    self.reporter.run_started(benchmarks.len() * variant_count);
+                    for benchmark in benchmarks {
+                        jobs.run(&self.options, &mut self.reporter, benchmark);
+                    }
+                }
+                let result = jobs.join();
+                // Remove directories left after removing temporary outputs.
+                self.compare_only_dir.take();
+                if let Err(err) = result {
+                    self.reporter.error(&err);
+                    return Err(err);
+                }
+            }
+            BenchModeData::PrintResults { collected } => {
+                if let Some(benchmarks) = collected.take() {
+                    let variant_count = self.options.geometry_variants().len();
+                    self.reporter.run_started(benchmarks.len() * variant_count);
+                    for benchmark in benchmarks {
+                        Self::print_results(&self.options, &mut self.reporter, benchmark);
+                    }
+                }
+            }
+            BenchModeData::List { ids } => {
+                let format = self.options.format;
+                if let Err(err) = PrintingReporter::report_list(ids, format, &mut io::stdout()) {
+                    let err = BenchError::List(err);
+                    self.reporter.error(&err);
+                    return Err(err);
+                }
+            }
+            BenchModeData::Test { should_fail } if *should_fail => {
+                let err = BenchError::TestFailures;
+                self.reporter.error(&err);
+                return Err(err);
+            }
+            _ => { /* no special handling required */ }
+        }
+
+        if let Some(html_reporter) = self.html_reporter.take() {
+            if let Err(error) = html_reporter.write_to_file() {
+                let err = BenchError::Report {
+                    path: html_reporter.out_path().to_owned(),
+                    error,
+                };
+                self.reporter.error(&err);
+                return Err(err);
+            }
+        }
+        mem::take(&mut self.reporter).ok_all();
+        Ok(())
+    }
+
+    /// Checks that the benchmark filter and tag filter (if any) have matched at least one benchmark
+    /// unless `--allow-no-matches` is specified. The check is skipped in the test mode since the filter may target
+    /// tests in other targets (e.g., `cargo test -- fib`). If the matches file is specified, the check outcome
+    /// is recorded to it instead, so that the caller (e.g., `cargo yab`) can check matches across all bench targets.
+    fn check_matches(&self) -> Result<(), BenchError> {
+        if matches!(self.mode, BenchModeData::Test { .. }) {
+            return Ok(());
+        }
+
+        let result = self.match_filters();
+        if let Some(path) = &self.options.matches_file {
+            if let Err(err) = Self::record_matches(path, result.as_ref().err()) {
+                let err = format!("Failed recording filter matches to `{path}`: {err}");
+                self.printer().report_warning(None, &err);
+            }
+            return Ok(());
+        }
+        match result {
+            Err(err) if self.options.allow_no_matches => {
+                if matches!(&err, BenchError::NoMatches { filter, exact: true } if has_regex_operators(filter))
+                {
+                    self.printer().report_warning(None, &err);
+                }
+                Ok(())
+            }
+            _ => result,
+        }
+    }
+
+    /// Appends a line to the matches file: either `matched`, or `unmatched: ` followed by the error message.
+    fn record_matches(path: &str, err: Option<&BenchError>) -> io::Result<()> {
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        match err {
+            None => writeln!(file, "matched"),
+            Some(err) => writeln!(file, "unmatched: {err}"),
+        }
+    }
+
+    fn printer(&self) -> PrintingReporter {
+        PrintingReporter::new(self.options.styling(), self.options.verbosity())
+    }
+
+    fn match_filters(&self) -> Result<(), BenchError> {
+        if !self.has_id_matches {
+            match &self.id_matcher {
+                IdMatcher::Any => { /* no filter to check */ }
+                IdMatcher::Regex(regex) => {
+                    return Err(BenchError::NoMatches {
+                        filter: regex.to_string(),
+                        exact: false,
+                    });
+                }
+                IdMatcher::Exact(filter) => {
+                    return Err(BenchError::NoMatches {
+                        filter: filter.clone(),
+                        exact: true,
+                    });
+                }
+            }
+        }
+        let tags = self.tag_matcher.tags();
+        if !self.has_tag_matches && !tags.is_empty() {
+            return Err(BenchError::NoTagMatches(tags.to_vec()));
+        }
+        Ok(())
+    }
+
+    fn matches(&mut self, id: &BenchmarkId) -> bool {
+        if !self.id_matcher.matches(id) {
+            return false;
+        }
+        self.has_id_matches = true;
+        if !self.tag_matcher.matches(id) {
+            return false;
         }
+        self.has_tag_matches = true;
+        self.file_matcher.matches(id)
     }
 
-    fn bench<T>(&mut self, id: BenchmarkId, mut bench_fn: impl FnMut(Capture) -> T) {
-        if !self.id_matcher.matches(&id) {
+    fn bench<T>(
+        &mut self,
+        id: BenchmarkId,
+        config: BenchConfig,
+        mut bench_fn: impl FnMut(Capture) -> T,
+    ) {
+        if !self.matches(&id) {
             return;
         }
 
@@ -155,62 +783,157 @@ impl MainBencher {
                 }
                 test_reporter.ok();
             }
+            _ => self.register(id, config, BenchTarget::Function),
+        }
+    }
+
+    fn bench_command(&mut self, id: BenchmarkId, command: ExternalCommand) {
+        if !self.matches(&id) {
+            return;
+        }
+
+        if let BenchModeData::Test { should_fail } = &mut self.mode {
+            let test_reporter = self.reporter.new_test(&id);
+            // Run the command once w/o `cachegrind`.
+            let output = command.to_command().output();
+            let message = match output {
+                Ok(output) if output.status.success() => {
+                    test_reporter.ok();
+                    return;
+                }
+                Ok(output) => {
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    format!(
+                        "command {:?} failed with {}\n{}",
+                        command.to_command(),
+                        output.status,
+                        stderr.trim()
+                    )
+                }
+                Err(err) => format!("failed spawning command {:?}: {err}", command.to_command()),
+            };
+            // Similar to panics in benchmarked functions, output the failure details to stderr.
+            eprintln!("{message}");
+            test_reporter.fail(&message);
+            *should_fail = true;
+        } else {
+            self.register(id, BenchConfig::default(), BenchTarget::Command(command));
+        }
+    }
+
+    /// Registers a benchmark in the modes other than the test one.
+    fn register(&mut self, id: BenchmarkId, config: BenchConfig, target: BenchTarget) {
+        match &mut self.mode {
+            BenchModeData::Test { .. } => unreachable!("tests are run on registration"),
+            BenchModeData::Bench {
+                collected: Some(benchmarks),
+                ..
+            }
+            | BenchModeData::PrintResults {
+                collected: Some(benchmarks),
+            } => {
+                benchmarks.push((id, config, target));
+            }
             BenchModeData::Bench {
-                jobs_semaphore,
                 jobs,
-                this_executable,
+                collected: None,
             } => {
-                let executor = CachegrindRunner {
-                    options: self.options.clone(),
-                    this_executable: this_executable.to_owned(),
-                    reporter: self.reporter.new_benchmark(&id),
-                    id,
-                };
-
-                if jobs_semaphore.capacity() == 1 {
-                    // Run the executor synchronously in order to have deterministic ordering
-                    executor.run_benchmark();
-                } else {
-                    let jobs_semaphore = jobs_semaphore.clone();
-                    jobs.push(thread::spawn(move || {
-                        let _permit = jobs_semaphore.acquire_owned();
-                        executor.run_benchmark();
-                    }));
-                }
+                jobs.run(&self.options, &mut self.reporter, (id, config, target));
             }
-            BenchModeData::List => {
-                PrintingReporter::report_list_item(&id);
+            BenchModeData::PrintResults { collected: None } => {
+                Self::print_results(&self.options, &mut self.reporter, (id, config, target));
             }
-            BenchModeData::PrintResults => {
-                let executor = CachegrindRunner {
-                    options: self.options.clone(),
-                    reporter: self.reporter.new_benchmark(&id),
-                    // `this_executable` isn't used, so it's fine to set it to an empty string
-                    this_executable: String::new(),
-                    id,
-                };
-                executor.report_benchmark_result();
+            BenchModeData::List { ids } => {
+                ids.push(id);
+            }
+            BenchModeData::DryRun { this_executable } => {
+                for (label, options) in self.options.geometry_variants() {
+                    let runner = CachegrindRunner {
+                        options,
+                        config: config.clone(),
+                        target: target.clone(),
+                        this_executable: this_executable.clone(),
+                        reporter: Box::new(()),
+                        id: variant_id(&id, label.as_deref()),
+                        spawn_id: id.clone(),
+                        failures: Arc::default(),
+                    };
+                    runner.print_command();
+                }
             }
         }
     }
+
+    /// Prints previously saved results of the benchmark for all cache geometry variants.
+    fn print_results(
+        options: &BenchOptions,
+        reporter: &mut SeqReporter,
+        (id, config, target): (BenchmarkId, BenchConfig, BenchTarget),
+    ) {
+        for (label, options) in options.geometry_variants() {
+            let run_id = variant_id(&id, label.as_deref());
+            let runner = CachegrindRunner {
+                options,
+                config: config.clone(),
+                target: target.clone(),
+                reporter: reporter.new_benchmark(&run_id),
+                // `this_executable` isn't used, so it's fine to set it to an empty string
+                this_executable: String::new(),
+                id: run_id,
+                spawn_id: id.clone(),
+                failures: Arc::default(),
+            };
+            runner.report_benchmark_result();
+        }
+    }
+}
+
+/// Computes the number of instructions per benchmark iteration. The calibration and baseline runs only differ
+/// in the number of iterations (1 and `iterations`, respectively), so the difference between their instruction counts
+/// excludes the constant overhead (e.g., process startup). If the baseline run is skipped, the calibration total
+/// is the best available estimate.
+fn per_iter_instructions(
+    calibration_instructions: u64,
+    baseline_instructions: u64,
+    iterations: u64,
+) -> u64 {
+    if iterations > 1 {
+        baseline_instructions.saturating_sub(calibration_instructions) / (iterations - 1)
+    } else {
+        calibration_instructions
+    }
+}
+
+/// Returns the ID of a benchmark run with the specified cache geometry label.
+fn variant_id(id: &BenchmarkId, geometry_label: Option<&str>) -> BenchmarkId {
+    match geometry_label {
+        Some(label) => id.clone().renamed(format!("{id} @ {label}")),
+        None => id.clone(),
+    }
 }
 
 /// Runner for a single benchmark.
 #[derive(Debug)]
 struct CachegrindRunner {
     options: BenchOptions,
+    config: BenchConfig,
+    target: BenchTarget,
     this_executable: String,
     reporter: Box<dyn BenchmarkReporter>,
+    /// ID used for reporting and output paths.
     id: BenchmarkId,
+    /// ID passed to the instrumented executable. Differs from `id` if the cache geometry is swept.
+    spawn_id: BenchmarkId,
+    failures: SharedFailures,
 }
 
 macro_rules! unwrap_summary {
-    ($events:expr, $result:expr) => {
+    ($runner:ident, $result:expr) => {
         match $result {
             Ok(stats) => stats,
             Err(err) => {
-                $events.error(&err);
-                process::exit(1);
+                $runner.fail(err.into());
+                return;
             }
         }
     };
@@ -226,117 +949,595 @@ impl CachegrindRunner {
     ///    `(n + 1) * setup + (n + 1) * bench + const`.
     /// 4. Subtract baseline stats from the full stats. The difference is equal to `bench`.
     fn run_benchmark(mut self) {
-        let final_baseline_path = format!(
-            "{}/{}.baseline.cachegrind",
-            self.options.cachegrind_out_dir, self.id
-        );
-        let final_full_path = format!("{}/{}.cachegrind", self.options.cachegrind_out_dir, self.id);
-        let old_baseline = self.load_and_backup_summary(&final_baseline_path);
-        let prev_stats = old_baseline.and_then(|baseline| {
-            let full = self.load_and_backup_summary(&final_full_path)?;
-            Some(full - baseline)
-        });
+        if let BenchTarget::Command(command) = &self.target {
+            let command = command.clone();
+            self.run_command_benchmark(&command);
+            return;
+        }
+        if self.options.fast {
+            self.run_approximate_benchmark();
+            return;
+        }
 
-        let baseline_path = format!("{final_baseline_path}~");
-        let full_path = format!("{final_full_path}~");
+        let (final_baseline_path, final_full_path) = self.output_paths();
+        let prev_stats = self.load_prev_stats(&final_baseline_path, &final_full_path);
+        let (baseline_path, full_path) = self.temp_output_paths();
 
-        // Use `baseline_path` in case we won't run the baseline after calibration
-        let command = self.options.cachegrind_wrapper(&baseline_path);
         self.reporter.start_execution();
-        let cachegrind_result = cachegrind::spawn_instrumented(SpawnArgs {
-            command,
-            out_path: &baseline_path,
-            this_executable: &self.this_executable,
-            id: &self.id,
-            iterations: 2,
-            is_baseline: true,
-        });
-        let summary = unwrap_summary!(self.reporter, cachegrind_result);
+        let started_at = Instant::now();
+        let calibration_args = self.calibration_args(&baseline_path);
+        let cachegrind_result = cachegrind::spawn_instrumented(calibration_args);
+        let summary = unwrap_summary!(self, cachegrind_result);
 
-        // FIXME: handle `warm_up_instructions == 0` specially
-        let estimated_iterations = self.options.warm_up_instructions / summary.total_instructions();
-        let estimated_iterations = estimated_iterations.clamp(1, self.options.max_iterations);
+        let calibration_instructions = summary.total_instructions();
+        let estimated_iterations = self.estimate_iterations(calibration_instructions);
         let baseline = if estimated_iterations == 1 {
             summary
         } else {
             // This will override calibration output, which is exactly what we need.
-            let command = self.options.cachegrind_wrapper(&baseline_path);
-            let cachegrind_result = cachegrind::spawn_instrumented(SpawnArgs {
-                command,
-                out_path: &baseline_path,
-                this_executable: &self.this_executable,
-                id: &self.id,
-                iterations: estimated_iterations + 1,
-                is_baseline: true,
-            });
-            unwrap_summary!(self.reporter, cachegrind_result)
+            let spawn_args = self.spawn_args(&baseline_path, estimated_iterations + 1, true);
+            let cachegrind_result = cachegrind::spawn_instrumented(spawn_args);
+            unwrap_summary!(self, cachegrind_result)
         };
+
+        let per_iter_instructions = per_iter_instructions(
+            calibration_instructions,
+            baseline.total_instructions(),
+            estimated_iterations,
+        );
+        self.reporter
+            .calibrated(per_iter_instructions, estimated_iterations);
+        if !self.options.quiet {
+            if let Some(warning) =
+                self.under_warmed_warning(per_iter_instructions, estimated_iterations)
+            {
+                self.reporter.warning(&warning);
+            }
+        }
         self.reporter.baseline_computed(&baseline);
 
-        let command = self.options.cachegrind_wrapper(&full_path);
-        let cachegrind_result = cachegrind::spawn_instrumented(SpawnArgs {
-            command,
-            out_path: &full_path,
-            this_executable: &self.this_executable,
-            id: &self.id,
-            iterations: estimated_iterations + 1,
-            is_baseline: false,
+        let spawn_args = self.spawn_args(&full_path, estimated_iterations + 1, false);
+        let cachegrind_result = cachegrind::spawn_instrumented(spawn_args);
+        let full = unwrap_summary!(self, cachegrind_result);
+        let full_instructions = full.total_instructions();
+        let stats = unwrap_summary!(self, full.try_sub(baseline));
+        if self.options.fail_on_zero && stats.total_instructions() == 0 {
+            self.fail(BenchError::ZeroInstructions);
+            return;
+        }
+        if !self.options.quiet {
+            let captured_instructions = stats.total_instructions();
+            let iterations = estimated_iterations + 1;
+            self.check_captured_share(captured_instructions, full_instructions, iterations);
+        }
+
+        let paths = [
+            (baseline_path.as_str(), final_baseline_path.as_str()),
+            (full_path.as_str(), final_full_path.as_str()),
+        ];
+        let io_result = self.persist_outputs(&paths, stats);
+        unwrap_summary!(self, io_result);
+        if let Err(err) = self.check_budget(&stats) {
+            self.fail(err);
+            return;
+        }
+        self.check_collectors(&stats, prev_stats.as_ref());
+        let raw_output_path = self.raw_output_path(&final_full_path);
+        self.reporter.ok(&BenchmarkOutput {
+            stats,
+            prev_stats,
+            wall_time: Some(started_at.elapsed()),
+            is_approximate: false,
+            raw_output_path,
         });
-        let full = unwrap_summary!(self.reporter, cachegrind_result);
-        let stats = full - baseline;
+    }
 
-        // (Almost) atomically move cachegrind files to their final locations, so that the following benchmark runs
-        // don't output nonsense if the benchmark is interrupted. There's still a risk that the baseline file
-        // will get updated and the full output will be not, but it's significantly lower.
-        let io_result = fs::rename(&baseline_path, &final_baseline_path);
-        unwrap_summary!(self.reporter, io_result);
-        let io_result = fs::rename(&full_path, &final_full_path);
-        unwrap_summary!(self.reporter, io_result);
+    /// Runs the benchmark once without calibration or a baseline run. The output isn't persisted.
+    fn run_approximate_benchmark(mut self) {
+        let (_, full_path) = self.temp_output_paths();
+
+        self.reporter.start_execution();
+        let started_at = Instant::now();
+        let cachegrind_result =
+            cachegrind::spawn_instrumented(self.spawn_args(&full_path, 1, false));
+        let stats = unwrap_summary!(self, cachegrind_result);
+        self.remove_output(&full_path);
+        if self.options.fail_on_zero && stats.total_instructions() == 0 {
+            self.fail(BenchError::ZeroInstructions);
+            return;
+        }
 
-        self.reporter.ok(&BenchmarkOutput { stats, prev_stats });
+        self.reporter.ok(&BenchmarkOutput {
+            stats,
+            prev_stats: None,
+            wall_time: Some(started_at.elapsed()),
+            is_approximate: true,
+            raw_output_path: None,
+        });
     }
 
-    fn report_benchmark_result(mut self) {
-        let baseline_path = format!(
-            "{}/{}.baseline.cachegrind",
-            self.options.cachegrind_out_dir, self.id
+    /// Runs an external command under `cachegrind` once. There is no baseline run, so the stats
+    /// cover the entire command execution.
+    fn run_command_benchmark(mut self, command: &ExternalCommand) {
+        let (final_baseline_path, final_full_path) = self.output_paths();
+        let prev_stats = self.load_prev_stats(&final_baseline_path, &final_full_path);
+        let (_, full_path) = self.temp_output_paths();
+
+        self.reporter.start_execution();
+        let started_at = Instant::now();
+        let cachegrind_result = cachegrind::spawn(
+            self.external_command(command, &full_path),
+            self.options.tool,
+            &full_path,
+            self.options.show_output,
         );
-        let full_path = format!("{}/{}.cachegrind", self.options.cachegrind_out_dir, self.id);
-        let Some(baseline) = self.load_summary(&baseline_path) else {
-            self.reporter.warning(&"no data for benchmark");
+        let stats = unwrap_summary!(self, cachegrind_result);
+        if self.options.fail_on_zero && stats.total_instructions() == 0 {
+            self.fail(BenchError::ZeroInstructions);
             return;
-        };
-        let Some(full) = self.load_summary(&full_path) else {
-            self.reporter.warning(&"no data for benchmark");
+        }
+        let io_result = self.persist_outputs(&[(&full_path, &final_full_path)], stats);
+        unwrap_summary!(self, io_result);
+        if let Err(err) = self.check_budget(&stats) {
+            self.fail(err);
             return;
-        };
-        let stats = full - baseline;
-
-        let old_baseline_path = format!("{baseline_path}.old");
-        let old_full_path = format!("{full_path}.old");
-        let old_baseline = self.load_summary(&old_baseline_path);
-        let prev_stats =
-            old_baseline.and_then(|baseline| Some(self.load_summary(&old_full_path)? - baseline));
+        }
+        self.check_collectors(&stats, prev_stats.as_ref());
+        let raw_output_path = self.raw_output_path(&final_full_path);
+        self.reporter.ok(&BenchmarkOutput {
+            stats,
+            prev_stats,
+            wall_time: Some(started_at.elapsed()),
+            is_approximate: false,
+            raw_output_path,
+        });
+    }
 
-        self.reporter.ok(&BenchmarkOutput { stats, prev_stats });
+    /// Returns the command spawning an external program under the `cachegrind` wrapper.
+    fn external_command(&self, command: &ExternalCommand, out_path: &str) -> Command {
+        // The wrapper inherits the working directory of the command, so the output path must not depend on it.
+        let out_path = if command.has_current_dir() {
+            absolute_path(out_path)
+        } else {
+            out_path.to_owned()
+        };
+        let mut wrapper = self.options.cachegrind_wrapper(&out_path);
+        // External programs don't issue client requests to start instrumentation.
+        let is_instrumented_on_request = wrapper.get_args().any(|arg| arg == "--instr-at-start=no");
+        if self.options.tool == Tool::Cachegrind && is_instrumented_on_request {
+            wrapper.arg("--instr-at-start=yes");
+        }
+        command.append_to(wrapper)
     }
 
-    fn load_summary(&mut self, path: &str) -> Option<CachegrindStats> {
-        fs::File::open(path)
-            .ok()
-            .and_then(|file| match CachegrindStats::new(file, path) {
-                Ok(summary) => Some(summary),
+    /// Moves `cachegrind` outputs from the temporary paths to the final ones, updates the history and removes
+    /// the outputs if necessary. With `--compare-only`, the temporary outputs are removed instead.
+    fn persist_outputs(
+        &mut self,
+        paths: &[(&str, &str)],
+        stats: CachegrindStats,
+    ) -> Result<(), BenchError> {
+        if self.options.compare_only {
+            for &(temp_path, _) in paths {
+                self.remove_output(temp_path);
+            }
+            return Ok(());
+        }
+
+        // (Almost) atomically move cachegrind files to their final locations, so that the following benchmark runs
+        // don't output nonsense if the benchmark is interrupted. There's still a risk that the baseline file
+        // will get updated and the full output will be not, but it's significantly lower.
+        for &(temp_path, final_path) in paths {
+            fs::rename(temp_path, final_path).map_err(|error| BenchError::MoveOutput {
+                from: temp_path.to_owned(),
+                to: final_path.to_owned(),
+                error,
+            })?;
+        }
+        if self.options.history > 0 {
+            self.update_history(stats);
+        }
+        if self.options.keep_cachegrind_output != KeepOutput::Always {
+            for &(_, path) in paths {
+                self.remove_output(path);
+                self.remove_output(&format!("{path}.old"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the path to the full `cachegrind` output if it's retained after processing.
+    fn raw_output_path(&self, final_full_path: &str) -> Option<PathBuf> {
+        let is_retained =
+            !self.options.compare_only && self.options.keep_cachegrind_output == KeepOutput::Always;
+        is_retained.then(|| PathBuf::from(final_full_path))
+    }
+
+    /// Loads stats to compare the benchmark against, backing up the current outputs unless `--compare-only`
+    /// is specified.
+    fn load_prev_stats(&mut self, baseline_path: &str, full_path: &str) -> Option<CachegrindStats> {
+        let mut prev_stats = if self.options.compare_only {
+            self.load_stats(baseline_path, full_path)
+        } else {
+            self.load_and_backup_stats(baseline_path, full_path)
+        };
+        if let Some(vs_dir) = self.options.vs_dir.clone() {
+            prev_stats = self.load_stats_from_dir(&vs_dir);
+        } else if self.options.vs != Comparison::Prev {
+            prev_stats = self
+                .load_history()
+                .and_then(|history| history.aggregate(self.options.vs));
+        }
+        prev_stats
+    }
+
+    /// Reports a fatal benchmark error. Other benchmarks will still run unless `--fail-fast` is specified;
+    /// the process will exit with an error once all benchmarks are completed.
+    fn fail(mut self, err: BenchError) {
+        if self.options.keep_cachegrind_output == KeepOutput::Never {
+            let (baseline_path, full_path) = self.temp_output_paths();
+            self.remove_output(&baseline_path);
+            self.remove_output(&full_path);
+        }
+        self.reporter.error(&err);
+        let mut failures = self.failures.lock().unwrap_or_else(PoisonError::into_inner);
+        failures.push((self.id, err));
+    }
+
+    /// Reports that the benchmark is skipped because another benchmark has failed and `--fail-fast` is specified.
+    /// Unlike [`Self::fail()`], the benchmark isn't recorded as a failure.
+    fn skip(self) {
+        self.reporter
+            .error(&"skipped because of a previous benchmark failure (`--fail-fast`)");
+    }
+
+    fn remove_output(&mut self, path: &str) {
+        match fs::remove_file(path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => {
+                let err = format!("Failed removing cachegrind output `{path}`: {err}");
+                self.reporter.warning(&err);
+            }
+        }
+    }
+
+    /// Returns args for the calibration run.
+    fn calibration_args<'a>(&'a self, baseline_path: &'a str) -> SpawnArgs<'a> {
+        // Use `baseline_path` in case we won't run the baseline after calibration
+        self.spawn_args(baseline_path, 2, true)
+    }
+
+    fn spawn_args<'a>(
+        &'a self,
+        out_path: &'a str,
+        iterations: u64,
+        is_baseline: bool,
+    ) -> SpawnArgs<'a> {
+        SpawnArgs {
+            tool: self.options.tool,
+            command: self.options.cachegrind_wrapper(out_path),
+            out_path,
+            this_executable: &self.this_executable,
+            id: &self.spawn_id,
+            iterations,
+            is_baseline,
+            seed: self.options.seed,
+            show_output: self.options.show_output,
+        }
+    }
+
+    /// Prints commands that would be spawned for the benchmark: calibration, baseline and full runs
+    /// for functions, or a single run for external commands and with `--fast`. The number of iterations
+    /// for the baseline and full runs is determined by calibration, so the maximum possible number is printed.
+    /// The baseline run is omitted if it would be skipped (i.e., if the calibration run doubles as the baseline).
+    fn print_command(&self) {
+        let (baseline_path, full_path) = self.temp_output_paths();
+        let id = &self.id;
+        if let BenchTarget::Command(command) = &self.target {
+            let command = self.external_command(command, &full_path);
+            println!("{id}: {command:?}");
+            return;
+        }
+        if self.options.fast {
+            let command = self.spawn_args(&full_path, 1, false).into_command();
+            println!("{id}: {command:?}");
+            return;
+        }
+
+        let command = self.calibration_args(&baseline_path).into_command();
+        println!("{id} [calibration]: {command:?}");
+        // Calibration with a single instruction per iteration yields the maximum number of iterations.
+        let max_iterations = self.estimate_iterations(1);
+        if max_iterations > 1 {
+            let args = self.spawn_args(&baseline_path, max_iterations + 1, true);
+            println!("{id} [baseline]: {:?}", args.into_command());
+        }
+        let args = self.spawn_args(&full_path, max_iterations + 1, false);
+        println!("{id} [full]: {:?}", args.into_command());
+    }
+
+    /// Returns paths to the baseline and full outputs produced by `cachegrind` before they are processed.
+    /// With `--compare-only`, the outputs are placed in a temporary directory.
+    fn temp_output_paths(&self) -> (String, String) {
+        let (baseline_path, full_path) = if let Some(dir) = &self.options.compare_only_dir {
+            self.output_paths_in(dir)
+        } else {
+            self.output_paths()
+        };
+        (format!("{baseline_path}~"), format!("{full_path}~"))
+    }
+
+    /// Returns paths to the baseline and full outputs, respectively.
+    fn output_paths(&self) -> (String, String) {
+        self.output_paths_in(&self.options.cachegrind_out_dir())
+    }
+
+    /// Returns paths to the baseline and full outputs in the specified directory.
+    fn output_paths_in(&self, out_dir: &str) -> (String, String) {
+        let extension = self.options.tool.extension();
+        let id_path = self.id.to_path();
+        (
+            format!("{out_dir}/{id_path}.baseline.{extension}"),
+            format!("{out_dir}/{id_path}.{extension}"),
+        )
+    }
+
+    fn warm_up_instructions(&self) -> u64 {
+        self.config
+            .warm_up_instructions
+            .unwrap_or(self.options.warm_up_instructions)
+    }
+
+    fn max_iterations(&self) -> u64 {
+        self.config
+            .max_iterations
+            .unwrap_or(self.options.max_iterations)
+    }
+
+    /// Estimates the number of warm-up iterations based on the number of instructions in the calibration run,
+    /// and adds `--warmup-runs` iterations. Zero warm-up (without additional runs) means a single measured iteration;
+    /// in this case, the calibration run doubles as the baseline.
+    fn estimate_iterations(&self, calibration_instructions: u64) -> u64 {
+        let warm_up_instructions = self.warm_up_instructions();
+        let estimated_iterations = if warm_up_instructions == 0 {
+            1
+        } else {
+            let estimated_iterations = warm_up_instructions / calibration_instructions.max(1);
+            // `max_iterations` may be set to zero directly via the public `BenchConfig` field.
+            estimated_iterations.clamp(1, self.max_iterations().max(1))
+        };
+        estimated_iterations.saturating_add(self.options.warmup_runs)
+    }
+
+    /// Returns a warning if the number of iterations was clamped by `--max-iterations`, so that
+    /// the warm-up target isn't reached.
+    fn under_warmed_warning(&self, per_iter_instructions: u64, iterations: u64) -> Option<String> {
+        let warm_up_instructions = self.warm_up_instructions();
+        let is_clamped = iterations.saturating_sub(self.options.warmup_runs)
+            == self.max_iterations()
+            && per_iter_instructions.saturating_mul(iterations) < warm_up_instructions;
+        is_clamped.then(|| {
+            let iterations = iterations_str(iterations);
+            format!(
+                "benchmark may be under-warmed: the warm-up target of {warm_up_instructions} instructions \
+                 isn't reached with {iterations} of ~{per_iter_instructions} instructions each; \
+                 consider raising `--max-iterations` or lowering `--warm-up`"
+            )
+        })
+    }
+
+    fn check_captured_share(
+        &mut self,
+        captured_instructions: u64,
+        full_instructions: u64,
+        iterations: u64,
+    ) {
+        if let Some(warning) =
+            self.small_capture_warning(captured_instructions, full_instructions, iterations)
+        {
+            self.reporter.warning(&warning);
+        }
+    }
+
+    /// Returns a warning if the captured instructions are a small share of instructions executed per iteration
+    /// in the full run (i.e., including setup). The fixed overhead of the full run is amortized over iterations,
+    /// so the share is slightly underestimated.
+    fn small_capture_warning(
+        &self,
+        captured_instructions: u64,
+        full_instructions: u64,
+        iterations: u64,
+    ) -> Option<String> {
+        let min_share = f64::from(self.options.min_captured_share) / 100.0;
+        if min_share == 0.0 || full_instructions == 0 {
+            return None;
+        }
+        #[allow(clippy::cast_precision_loss)] // acceptable precision loss
+        let share = captured_instructions as f64 * iterations as f64 / full_instructions as f64;
+        (share < min_share).then(|| {
+            format!(
+                "captured region accounts for only {:.1}% of instructions per iteration; the benchmark may be \
+                 dominated by setup, or the capture may be misplaced (use `--min-captured-share` to tune this warning)",
+                share * 100.0
+            )
+        })
+    }
+
+    /// Checks that the captured instructions are within the budget (if any). The stats are still persisted
+    /// if the budget is exceeded.
+    fn check_budget(&self, stats: &CachegrindStats) -> Result<(), BenchError> {
+        let actual = stats.total_instructions();
+        match self.config.instruction_budget {
+            Some(budget) if actual > budget => Err(BenchError::BudgetExceeded { budget, actual }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Warns if the previous stats were collected with a different collector configuration
+    /// (e.g., with cache simulation switched off), in which case only instructions are compared.
+    fn check_collectors(&mut self, stats: &CachegrindStats, prev_stats: Option<&CachegrindStats>) {
+        let Some(prev_stats) = prev_stats else {
+            return;
+        };
+        let (collector, prev_collector) = (stats.collector(), prev_stats.collector());
+        if collector != prev_collector {
+            let warning = format!(
+                "previous stats were collected with {prev_collector}, but the current stats are collected \
+                 with {collector}; only instruction counts will be compared"
+            );
+            self.reporter.warning(&warning);
+        }
+    }
+
+    fn report_benchmark_result(mut self) {
+        let (baseline_path, full_path) = self.output_paths();
+        let baseline = match self.target {
+            BenchTarget::Function => {
+                let Some(baseline) = self.load_summary(&baseline_path) else {
+                    self.reporter.warning(&"no data for benchmark");
+                    return;
+                };
+                Some(baseline)
+            }
+            BenchTarget::Command(_) => None,
+        };
+        let Some(full) = self.load_summary(&full_path) else {
+            self.reporter.warning(&"no data for benchmark");
+            return;
+        };
+        let stats = match baseline.map_or(Ok(full), |baseline| full.try_sub(baseline)) {
+            Ok(stats) => stats,
+            Err(err) => {
+                self.reporter.error(&err);
+                return;
+            }
+        };
+
+        let prev_stats = if let Some(vs_dir) = self.options.vs_dir.clone() {
+            self.load_stats_from_dir(&vs_dir)
+        } else if self.options.vs != Comparison::Prev {
+            // Exclude the history entry recorded by the run that has produced the reported stats.
+            self.load_history().and_then(|mut history| {
+                history.truncate_latest_run();
+                history.aggregate(self.options.vs)
+            })
+        } else if self.options.no_backup {
+            None
+        } else {
+            self.load_stats(&format!("{baseline_path}.old"), &format!("{full_path}.old"))
+        };
+
+        self.check_collectors(&stats, prev_stats.as_ref());
+        self.reporter.ok(&BenchmarkOutput {
+            stats,
+            prev_stats,
+            wall_time: None,
+            is_approximate: false,
+            raw_output_path: None,
+        });
+    }
+
+    fn history_path(&self) -> String {
+        let out_dir = self.options.cachegrind_out_dir();
+        let id_path = self.id.to_path();
+        format!("{out_dir}/{id_path}.history.json")
+    }
+
+    fn load_history(&mut self) -> Option<History> {
+        match History::load(&self.history_path()) {
+            Ok(history) => Some(history),
+            Err(err) => {
+                self.reporter.warning(&err);
+                None
+            }
+        }
+    }
+
+    fn update_history(&mut self, stats: CachegrindStats) {
+        let Some(mut history) = self.load_history() else {
+            return;
+        };
+        history.push(stats, self.options.history);
+        let path = self.history_path();
+        if let Err(err) = history.save(&path) {
+            let err = format!("Failed saving benchmark history `{path}`: {err}");
+            self.reporter.warning(&err);
+        }
+    }
+
+    fn load_summary(&mut self, path: &str) -> Option<CachegrindStats> {
+        fs::File::open(path).ok().and_then(|file| {
+            match CachegrindStats::new(file, path, self.options.tool) {
+                Ok(summary) => Some(summary),
                 Err(err) => {
                     self.reporter.warning(&err);
                     None
                 }
-            })
+            }
+        })
+    }
+
+    /// Loads stats from the baseline and full outputs in the specified directory (e.g., produced by another run).
+    fn load_stats_from_dir(&mut self, dir: &str) -> Option<CachegrindStats> {
+        let (baseline_path, full_path) = self.output_paths_in(dir);
+        let stats = self.load_stats(&baseline_path, &full_path);
+        if stats.is_none() {
+            let warning = format!("no data for benchmark in `{dir}` to compare against");
+            self.reporter.warning(&warning);
+        }
+        stats
+    }
+
+    /// Loads stats from the baseline and full outputs. External commands have no baseline output.
+    fn load_stats(&mut self, baseline_path: &str, full_path: &str) -> Option<CachegrindStats> {
+        if let BenchTarget::Command(_) = &self.target {
+            return self.load_summary(full_path);
+        }
+        let baseline = self.load_summary(baseline_path)?;
+        let full = self.load_summary(full_path)?;
+        self.subtract_baseline(full, baseline, full_path)
+    }
+
+    /// Subtracts the baseline from full stats loaded from `full_path`. If the stats are incompatible (e.g.,
+    /// produced by different tools), reports a warning and returns `None`.
+    fn subtract_baseline(
+        &mut self,
+        full: CachegrindStats,
+        baseline: CachegrindStats,
+        full_path: &str,
+    ) -> Option<CachegrindStats> {
+        match full.try_sub(baseline) {
+            Ok(stats) => Some(stats),
+            Err(err) => {
+                let warning = format!("ignoring outputs at `{full_path}`: {err}");
+                self.reporter.warning(&warning);
+                None
+            }
+        }
+    }
+
+    /// Same as [`Self::load_stats()`], but also backs up the loaded outputs.
+    fn load_and_backup_stats(
+        &mut self,
+        baseline_path: &str,
+        full_path: &str,
+    ) -> Option<CachegrindStats> {
+        if let BenchTarget::Command(_) = &self.target {
+            return self.load_and_backup_summary(full_path);
+        }
+        let baseline = self.load_and_backup_summary(baseline_path)?;
+        let full = self.load_and_backup_summary(full_path)?;
+        self.subtract_baseline(full, baseline, full_path)
     }
 
     fn load_and_backup_summary(&mut self, path: &str) -> Option<CachegrindStats> {
         let summary = self.load_summary(path);
-        if summary.is_some() {
-            let backup_path = format!("{path}.old");
+        let backup_path = format!("{path}.old");
+        if self.options.no_backup {
+            // Remove the stale backup so that it's not compared against the new output.
+            self.remove_output(&backup_path);
+        } else if summary.is_some() {
             if let Err(err) = fs::copy(path, &backup_path) {
                 let err = format!("Failed backing up cachegrind baseline `{path}`: {err}");
                 self.reporter.warning(&err);
@@ -348,32 +1549,65 @@ impl CachegrindRunner {
 
 #[derive(Debug)]
 enum BencherInner {
-    Main(MainBencher),
+    Main(Box<MainBencher>),
     Cachegrind(CachegrindOptions),
 }
 
+type FormatId = dyn Fn(&BenchmarkId) -> String;
+
 /// Benchmarking manager providing ability to define and run benchmarks.
 ///
 /// # Examples
 ///
 /// See [crate docs](index.html) for the examples of usage.
-#[derive(Debug)]
 pub struct Bencher {
     inner: BencherInner,
+    id_formatter: Option<Box<FormatId>>,
+}
+
+impl fmt::Debug for Bencher {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("Bencher")
+            .field("inner", &self.inner)
+            .field("has_id_formatter", &self.id_formatter.is_some())
+            .finish()
+    }
 }
 
 /// Parses configuration options from the environment.
 impl Default for Bencher {
     fn default() -> Self {
-        let inner = match Options::new() {
-            Options::Bench(options) => BencherInner::Main(MainBencher::new(options)),
-            Options::Cachegrind(options) => BencherInner::Cachegrind(options),
-        };
-        Self { inner }
+        Self::with_args(env::args())
     }
 }
 
 impl Bencher {
+    /// Creates a bencher parsing configuration options from the provided command-line args
+    /// instead of [`env::args()`]. Similar to `env::args()`, the first arg must be the executable name.
+    ///
+    /// This is useful to embed benchmarks into a custom runner. Beware that in the benchmarking mode,
+    /// the executable specified by the first arg will be spawned under `cachegrind` with `--cachegrind-instrument ...`
+    /// args; these args must be passed to this method by the spawned process.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `args` are empty.
+    pub fn with_args(args: impl IntoIterator<Item = String>) -> Self {
+        let args: Vec<_> = args.into_iter().collect();
+        let this_executable = args.first().expect("no executable arg").clone();
+        let inner = match Options::from_args(args) {
+            Options::Bench(options) => {
+                BencherInner::Main(Box::new(MainBencher::new(options, &this_executable)))
+            }
+            Options::Cachegrind(options) => BencherInner::Cachegrind(options),
+        };
+        Self {
+            inner,
+            id_formatter: None,
+        }
+    }
+
     /// Adds a reporter to the bencher. Beware that bencher initialization may skew benchmark results.
     #[doc(hidden)] // not stable yet
     pub fn add_reporter(&mut self, reporter: impl Reporter + 'static) -> &mut Self {
@@ -383,6 +1617,83 @@ impl Bencher {
         self
     }
 
+    /// Adds a processor of benchmark outputs to the bencher. See [`BenchmarkProcessor`] docs for details.
+    pub fn with_processor(&mut self, processor: impl BenchmarkProcessor + 'static) -> &mut Self {
+        self.add_reporter(ProcessingReporter::new(processor))
+    }
+
+    /// Overrides the formula used to estimate CPU cycles in the benchmark output based on memory access stats.
+    /// By default, [`AccessSummary::estimated_cycles()`] is used. The estimate is only output for benchmarks
+    /// with cache simulation.
+    pub fn with_cycles_estimator(
+        &mut self,
+        estimator: impl Fn(&AccessSummary) -> u64 + Send + Sync + 'static,
+    ) -> &mut Self {
+        if let BencherInner::Main(bencher) = &self.inner {
+            bencher.cycles_estimator.set(estimator);
+        }
+        self
+    }
+
+    /// Sets the formatter transforming benchmark IDs, e.g. to rename benchmarks or strip sensitive info.
+    /// The formatted IDs are used everywhere instead of the original ones: for filtering, reporting,
+    /// and as paths to `cachegrind` outputs. Hence, the formatter must be deterministic and should not map
+    /// distinct IDs to the same string. Tags and benchmark locations are retained.
+    ///
+    /// The formatter only applies to benchmarks defined after it is set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use yab::{black_box, Bencher};
+    ///
+    /// fn benchmarks(bencher: &mut Bencher) {
+    ///     bencher
+    ///         .with_id_formatter(|id| format!("parsing/{id}"))
+    ///         .bench("u64", || black_box("1000").parse::<u64>()); // reported as `parsing/u64`
+    /// }
+    /// # yab::main!(benchmarks);
+    /// ```
+    pub fn with_id_formatter(
+        &mut self,
+        formatter: impl Fn(&BenchmarkId) -> String + 'static,
+    ) -> &mut Self {
+        self.id_formatter = Some(Box::new(formatter));
+        self
+    }
+
+    /// Completes running or otherwise processing (e.g., listing) the defined benchmarks, returning an error
+    /// instead of terminating the process on failure. Errors are still reported to the output. This method
+    /// is called by [`main!`](crate::main).
+    ///
+    /// If this method is not called, the benchmarks are completed when the bencher is dropped, and
+    /// the process is terminated with a non-zero exit code on failure. Calling this method is preferable
+    /// if the bencher is embedded into a larger process. Beware that the bencher may still terminate the process
+    /// on initialization (e.g., if command-line args are invalid or `cachegrind` is not available).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any benchmark or test has failed, or if the benchmark filter hasn't matched
+    /// any benchmarks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use yab::{black_box, Bencher};
+    ///
+    /// let mut bencher = Bencher::with_args(["bench", "--quiet"].map(str::to_owned));
+    /// bencher.bench("sum", || (0..black_box(1_000_u64)).sum::<u64>());
+    /// bencher.try_run().expect("benchmarks failed");
+    /// ```
+    pub fn try_run(mut self) -> Result<(), BenchError> {
+        match &mut self.inner {
+            BencherInner::Main(bencher) => bencher.finish(),
+            // The instrumented process terminates after running the instrumented benchmark;
+            // if it wasn't encountered, there's nothing to do.
+            BencherInner::Cachegrind(_) => Ok(()),
+        }
+    }
+
     /// Gets the benchmarking mode.
     pub fn mode(&self) -> BenchMode {
         match &self.inner {
@@ -391,6 +1702,45 @@ impl Bencher {
         }
     }
 
+    /// Gets the seed for benchmarks, e.g. to generate random inputs reproducibly. The seed can be set
+    /// via the `--seed` command-line arg; if not set, a fixed default value is used.
+    pub fn seed(&self) -> u64 {
+        match &self.inner {
+            BencherInner::Main(bencher) => bencher.options.seed,
+            BencherInner::Cachegrind(options) => options.seed,
+        }
+    }
+
+    /// Creates a setup shared among benchmarks, e.g. loading a dataset. The setup is performed lazily
+    /// at most once per process; see [`GlobalSetup`] docs for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use yab::{black_box, Bencher};
+    ///
+    /// fn benchmarks(bencher: &mut Bencher) {
+    ///     let values = bencher.global_setup(|| (0..10_000).collect::<Vec<u64>>());
+    ///     bencher
+    ///         .bench_with_capture("sum", |capture| {
+    ///             let values = values.get(); // performs the setup outside the captured region
+    ///             capture.measure(|| black_box(values).iter().sum::<u64>());
+    ///         })
+    ///         .bench_with_capture("max", |capture| {
+    ///             let values = values.get();
+    ///             capture.measure(|| black_box(values).iter().max().copied());
+    ///         });
+    /// }
+    /// # yab::main!(benchmarks);
+    /// ```
+    #[allow(clippy::unused_self)] // defined as a method for discoverability
+    pub fn global_setup<T, F: FnOnce() -> T>(&self, setup: F) -> GlobalSetup<T, F> {
+        GlobalSetup {
+            value: OnceCell::new(),
+            setup: Cell::new(Some(setup)),
+        }
+    }
+
     /// Benchmarks a single function. Dropping the output won't be included into the captured stats.
     #[track_caller]
     pub fn bench<T>(
@@ -398,12 +1748,125 @@ impl Bencher {
         id: impl Into<BenchmarkId>,
         mut bench_fn: impl FnMut() -> T,
     ) -> &mut Self {
-        self.bench_inner(id.into(), move |capture| {
+        self.bench_inner(id.into(), BenchConfig::default(), move |capture| {
             capture.measure(&mut bench_fn); // dropping the output is not included into capture
         });
         self
     }
 
+    /// Benchmarks a function over a shared reference to `input`. The input is created once by the caller
+    /// rather than on each benchmark iteration, and is passed through [`black_box`](crate::black_box)
+    /// before each call. Like with [`Self::bench()`], dropping the output isn't captured.
+    ///
+    /// This avoids cloning large inputs on each iteration. Since all iterations (including warm-up ones)
+    /// observe the same input, it must not be mutated by `bench_fn` (e.g., via interior mutability); otherwise,
+    /// the baseline and full `cachegrind` runs will execute different code, and the captured stats will be skewed.
+    /// Also, the input will usually be cached by the time of the captured iteration, so the stats
+    /// correspond to a warm cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use yab::Bencher;
+    ///
+    /// fn benchmarks(bencher: &mut Bencher) {
+    ///     let values: Vec<u64> = (0..10_000).collect();
+    ///     bencher.bench_refs("sum", &values, |values| values.iter().sum::<u64>());
+    /// }
+    /// # yab::main!(benchmarks);
+    /// ```
+    #[track_caller]
+    pub fn bench_refs<I: ?Sized, T>(
+        &mut self,
+        id: impl Into<BenchmarkId>,
+        input: &I,
+        mut bench_fn: impl FnMut(&I) -> T,
+    ) -> &mut Self {
+        self.bench_inner(id.into(), BenchConfig::default(), move |capture| {
+            let input = crate::black_box(input);
+            capture.measure(|| bench_fn(input));
+        });
+        self
+    }
+
+    /// Benchmarks dropping a value. The value is created by `setup` outside the captured region,
+    /// and only dropping it is captured. This is the opposite of [`Self::bench()`], which doesn't capture
+    /// dropping the output of the benchmarked function.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use yab::{black_box, Bencher};
+    ///
+    /// fn benchmarks(bencher: &mut Bencher) {
+    ///     bencher.bench_with_drop("drop_vec", || vec![black_box(1_u64); 1_000]);
+    /// }
+    /// # yab::main!(benchmarks);
+    /// ```
+    #[track_caller]
+    pub fn bench_with_drop<T>(
+        &mut self,
+        id: impl Into<BenchmarkId>,
+        mut setup: impl FnMut() -> T,
+    ) -> &mut Self {
+        self.bench_inner(id.into(), BenchConfig::default(), move |capture| {
+            let value = crate::black_box(setup());
+            let _guard = capture.start();
+            crate::black_box_drop(value);
+        });
+        self
+    }
+
+    /// Benchmarks an async function. The future is created by `bench_fn` outside the captured region,
+    /// and is then driven to completion by `block_on` inside it. `block_on` is usually provided by the async runtime
+    /// (e.g., `tokio::runtime::Runtime::block_on()`); the runtime should be created before calling this method
+    /// so that its setup isn't captured. Like with [`Self::bench()`], dropping the output isn't captured.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use yab::{black_box, Bencher};
+    /// # use std::future::Future;
+    ///
+    /// # fn block_on<F: Future>(future: F) -> F::Output {
+    /// #     use std::{pin::pin, sync::Arc, task::{Context, Poll, Wake}};
+    /// #     struct NoOpWaker;
+    /// #     impl Wake for NoOpWaker {
+    /// #         fn wake(self: Arc<Self>) {}
+    /// #     }
+    /// #     let waker = Arc::new(NoOpWaker).into();
+    /// #     let mut future = pin!(future);
+    /// #     loop {
+    /// #         if let Poll::Ready(output) = future.as_mut().poll(&mut Context::from_waker(&waker)) {
+    /// #             return output;
+    /// #         }
+    /// #     }
+    /// # }
+    /// async fn sum(values: &[u64]) -> u64 {
+    ///     values.iter().sum()
+    /// }
+    ///
+    /// fn benchmarks(bencher: &mut Bencher) {
+    ///     let values: Vec<u64> = (0..1_000).collect();
+    ///     // `block_on` is provided by the async runtime used
+    ///     bencher.bench_async("sum", || sum(black_box(&values)), block_on);
+    /// }
+    /// # yab::main!(benchmarks);
+    /// ```
+    #[track_caller]
+    pub fn bench_async<F: Future>(
+        &mut self,
+        id: impl Into<BenchmarkId>,
+        mut bench_fn: impl FnMut() -> F,
+        mut block_on: impl FnMut(F) -> F::Output,
+    ) -> &mut Self {
+        self.bench_inner(id.into(), BenchConfig::default(), move |capture| {
+            let future = bench_fn();
+            capture.measure(|| block_on(future));
+        });
+        self
+    }
+
     /// Benchmarks a function with configurable capture interval. This allows set up before starting the capture
     /// and/or post-processing (e.g., assertions) after the capture.
     #[track_caller]
@@ -412,14 +1875,70 @@ impl Bencher {
         id: impl Into<BenchmarkId>,
         bench_fn: impl FnMut(Capture),
     ) -> &mut Self {
-        self.bench_inner(id.into(), bench_fn);
+        self.bench_inner(id.into(), BenchConfig::default(), bench_fn);
+        self
+    }
+
+    /// Benchmarks a function with configurable capture interval, overriding the global options
+    /// with the provided `config`. Otherwise, works the same as [`Self::bench_with_capture()`].
+    #[track_caller]
+    pub fn bench_with_config(
+        &mut self,
+        id: impl Into<BenchmarkId>,
+        config: BenchConfig,
+        bench_fn: impl FnMut(Capture),
+    ) -> &mut Self {
+        self.bench_inner(id.into(), config, bench_fn);
         self
     }
 
-    fn bench_inner(&mut self, id: BenchmarkId, bench_fn: impl FnMut(Capture)) {
+    /// Benchmarks an external command, e.g. a CLI app. The command is spawned under `cachegrind` directly
+    /// (rather than via this executable) and is run once. Hence, the stats cover the entire command execution,
+    /// including process startup; there is no warm-up, and captures aren't supported.
+    ///
+    /// Only the program, args, env vars and the working directory of the `command` are used; the working directory
+    /// takes precedence over the `--working-dir` command-line option. In the test mode, the command is run once
+    /// without `cachegrind`, and the test fails if the command exits unsuccessfully.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::process::Command;
+    /// use yab::Bencher;
+    ///
+    /// fn benchmarks(bencher: &mut Bencher) {
+    ///     let mut command = Command::new("gzip");
+    ///     command.args(["--keep", "--force", "data.txt"]);
+    ///     bencher.bench_command("gzip", &command);
+    /// }
+    /// # yab::main!(benchmarks);
+    /// ```
+    #[track_caller]
+    pub fn bench_command(&mut self, id: impl Into<BenchmarkId>, command: &Command) -> &mut Self {
+        let id = self.format_id(id.into());
+        // In the `cachegrind` mode, there's nothing to do since the command is spawned by the main process.
+        if let BencherInner::Main(bencher) = &mut self.inner {
+            bencher.bench_command(id, ExternalCommand::from(command));
+        }
+        self
+    }
+
+    /// Applies the ID formatter, if any. The formatter must be applied both in the main and `cachegrind` modes,
+    /// so that the IDs match.
+    fn format_id(&self, id: BenchmarkId) -> BenchmarkId {
+        if let Some(formatter) = &self.id_formatter {
+            let name = formatter(&id);
+            id.renamed(name)
+        } else {
+            id
+        }
+    }
+
+    fn bench_inner(&mut self, id: BenchmarkId, config: BenchConfig, bench_fn: impl FnMut(Capture)) {
+        let id = self.format_id(id);
         match &mut self.inner {
             BencherInner::Main(bencher) => {
-                bencher.bench(id, bench_fn);
+                bencher.bench(id, config, bench_fn);
             }
             BencherInner::Cachegrind(options) => {
                 if id != options.id.as_str() {
@@ -430,3 +1949,552 @@ impl Bencher {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::Path,
+        pin,
+        sync::Mutex,
+        task::{Context, Poll, Wake},
+    };
+
+    use assert_matches::assert_matches;
+    use clap::Parser;
+
+    use super::*;
+    use crate::HeapStats;
+
+    #[derive(Debug)]
+    struct NoOpReporter;
+
+    impl BenchmarkReporter for NoOpReporter {
+        fn ok(self: Box<Self>, _output: &BenchmarkOutput) {
+            // do nothing
+        }
+    }
+
+    fn mock_runner(args: &[&str], config: BenchConfig) -> CachegrindRunner {
+        let args = ["yab"].iter().chain(args);
+        CachegrindRunner {
+            options: BenchOptions::parse_from(args),
+            config,
+            target: BenchTarget::Function,
+            this_executable: String::new(),
+            reporter: Box::new(NoOpReporter),
+            id: BenchmarkId::from("test"),
+            spawn_id: BenchmarkId::from("test"),
+            failures: Arc::default(),
+        }
+    }
+
+    #[test]
+    fn estimating_iterations() {
+        let runner = mock_runner(&["--warm-up=100000"], BenchConfig::default());
+        assert_eq!(runner.estimate_iterations(1_000), 100);
+        assert_eq!(runner.estimate_iterations(1_000_000), 1);
+        assert_eq!(runner.estimate_iterations(10), 1_000); // clamped by `max_iterations`
+
+        let config = BenchConfig::default().warm_up(1_000_000);
+        let runner = mock_runner(&["--warm-up=100000"], config);
+        assert_eq!(runner.estimate_iterations(1_000), 1_000);
+        assert_eq!(runner.estimate_iterations(10_000), 100);
+
+        let config = BenchConfig::default().warm_up(1_000_000);
+        let runner = mock_runner(&["--max-iterations=50"], config);
+        assert_eq!(runner.estimate_iterations(1_000), 50);
+    }
+
+    #[test]
+    fn estimating_iterations_with_per_benchmark_cap() {
+        let args = ["--warm-up=100000", "--max-iterations=500"];
+        let config = BenchConfig::default().max_iterations(20);
+        let runner = mock_runner(&args, config);
+        assert_eq!(runner.estimate_iterations(1_000), 20);
+        assert_eq!(runner.estimate_iterations(100_000), 1);
+        assert!(runner.under_warmed_warning(1_000, 20).is_some());
+
+        // The per-benchmark cap wins even if it's greater than the global one.
+        let config = BenchConfig::default().max_iterations(5_000);
+        let runner = mock_runner(&args, config);
+        assert_eq!(runner.estimate_iterations(10), 5_000);
+
+        // Other benchmarks use the global cap.
+        let runner = mock_runner(&args, BenchConfig::default());
+        assert_eq!(runner.estimate_iterations(10), 500);
+
+        // Zero cap set via the public field is treated as 1.
+        let config = BenchConfig {
+            max_iterations: Some(0),
+            ..BenchConfig::default()
+        };
+        let runner = mock_runner(&args, config);
+        assert_eq!(runner.estimate_iterations(10), 1);
+    }
+
+    #[test]
+    fn warning_about_under_warmed_benchmarks() {
+        let runner = mock_runner(
+            &["--warm-up=100000", "--max-iterations=50"],
+            BenchConfig::default(),
+        );
+        assert!(runner.under_warmed_warning(1_000, 50).is_some());
+        assert!(runner.under_warmed_warning(2_000, 50).is_none());
+        assert!(runner.under_warmed_warning(10_000, 10).is_none());
+
+        let warning = runner.under_warmed_warning(10, 50).unwrap();
+        assert!(warning.contains("~10 instructions"), "{warning}");
+        assert!(warning.contains("50 iterations"), "{warning}");
+        assert!(warning.contains("--max-iterations"), "{warning}");
+
+        let runner = mock_runner(&["--max-iterations=1"], BenchConfig::default());
+        let warning = runner.under_warmed_warning(10, 1).unwrap();
+        assert!(warning.contains(" 1 iteration "), "{warning}");
+    }
+
+    #[test]
+    fn computing_per_iteration_instructions() {
+        // Calibration: 1 iteration + 500 overhead; baseline: 10 iterations + 500 overhead
+        assert_eq!(per_iter_instructions(1_500, 10_500, 10), 1_000);
+        // Baseline is skipped
+        assert_eq!(per_iter_instructions(1_500, 1_500, 1), 1_500);
+        // Noisy outputs
+        assert_eq!(per_iter_instructions(1_500, 1_000, 10), 0);
+    }
+
+    #[test]
+    fn checking_instruction_budget() {
+        let runner = mock_runner(&[], BenchConfig::default());
+        runner
+            .check_budget(&CachegrindStats::simple(1_000_000))
+            .unwrap();
+
+        let config = BenchConfig::default().budget_instructions(2_000);
+        let runner = mock_runner(&[], config);
+        runner
+            .check_budget(&CachegrindStats::simple(2_000))
+            .unwrap();
+        let err = runner
+            .check_budget(&CachegrindStats::simple(2_500))
+            .unwrap_err();
+        assert_matches!(
+            err,
+            BenchError::BudgetExceeded {
+                budget: 2_000,
+                actual: 2_500
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "benchmark has captured 2500 instructions, exceeding its budget of 2000 instructions by 500 (+25.00%)"
+        );
+    }
+
+    #[test]
+    fn warning_about_small_captures() {
+        let runner = mock_runner(&[], BenchConfig::default());
+        // 10 iterations with 100 setup and 50 captured instructions each, plus 1000 fixed instructions
+        assert!(runner.small_capture_warning(50, 2_500, 10).is_none());
+        let warning = runner.small_capture_warning(5, 2_050, 10).unwrap();
+        assert!(warning.contains("only 2.4%"), "{warning}");
+
+        let runner = mock_runner(&["--min-captured-share=0"], BenchConfig::default());
+        assert!(runner.small_capture_warning(0, 2_000, 10).is_none());
+    }
+
+    #[derive(Debug, Default)]
+    struct WarningsReporter(Arc<Mutex<Vec<String>>>);
+
+    impl BenchmarkReporter for WarningsReporter {
+        fn ok(self: Box<Self>, _output: &BenchmarkOutput) {
+            // do nothing
+        }
+
+        fn warning(&mut self, warning: &dyn fmt::Display) {
+            self.0.lock().unwrap().push(warning.to_string());
+        }
+    }
+
+    #[test]
+    fn warning_about_collector_mismatch() {
+        let mut runner = mock_runner(&[], BenchConfig::default());
+        let warnings = Arc::<Mutex<_>>::default();
+        runner.reporter = Box::new(WarningsReporter(warnings.clone()));
+
+        let simple_stats = CachegrindStats::simple(100);
+        runner.check_collectors(&simple_stats, None);
+        runner.check_collectors(&simple_stats, Some(&CachegrindStats::simple(120)));
+        assert!(warnings.lock().unwrap().is_empty());
+
+        let heap = HeapStats {
+            allocations: 1,
+            bytes: 16,
+        };
+        let heap_stats = CachegrindStats::with_heap(100, heap);
+        runner.check_collectors(&simple_stats, Some(&heap_stats));
+        let warnings = warnings.lock().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("collected with DHAT"), "{warnings:?}");
+        assert!(
+            warnings[0].contains("with cachegrind w/o cache simulation"),
+            "{warnings:?}"
+        );
+    }
+
+    #[test]
+    fn creating_bencher_with_args() {
+        let args = ["bench", "--list"].map(str::to_owned);
+        let bencher = Bencher::with_args(args);
+        assert_eq!(bencher.mode(), BenchMode::List);
+
+        let mut call_count = 0;
+        let args = ["bench", "--quiet", "--exact", "counted"].map(str::to_owned);
+        let mut bencher = Bencher::with_args(args);
+        assert_eq!(bencher.mode(), BenchMode::Test);
+        bencher
+            .bench("counted", || call_count += 1)
+            .bench("skipped", || unreachable!());
+        assert_eq!(call_count, 1);
+    }
+
+    #[derive(Debug, Default)]
+    struct RecordingReporter(Arc<Mutex<Vec<String>>>);
+
+    impl Reporter for RecordingReporter {
+        fn run_started(&mut self, total: usize) {
+            self.0.lock().unwrap().push(format!("started: {total}"));
+        }
+
+        fn new_benchmark(&mut self, id: &BenchmarkId) -> Box<dyn BenchmarkReporter> {
+            self.0.lock().unwrap().push(format!("benchmark: {id}"));
+            Box::new(())
+        }
+    }
+
+    #[test]
+    fn collecting_benchmarks_first() {
+        let events = Arc::<Mutex<Vec<_>>>::default();
+        let args = ["bench", "--print", "--quiet"].map(str::to_owned);
+        let mut bencher = Bencher::with_args(args);
+        bencher.add_reporter(RecordingReporter(events.clone()));
+        bencher.bench("a", || 1 + 1);
+        // Benchmarks are processed as soon as they are defined.
+        assert_eq!(*events.lock().unwrap(), ["benchmark: a"]);
+        drop(bencher);
+        assert_eq!(*events.lock().unwrap(), ["benchmark: a"]);
+
+        let events = Arc::<Mutex<Vec<_>>>::default();
+        let args = ["bench", "--print", "--quiet", "--collect-first"].map(str::to_owned);
+        let mut bencher = Bencher::with_args(args);
+        bencher.add_reporter(RecordingReporter(events.clone()));
+        bencher.bench("a", || 1 + 1).bench("b", || 2 + 2);
+        assert!(events.lock().unwrap().is_empty());
+        drop(bencher);
+        assert_eq!(
+            *events.lock().unwrap(),
+            ["started: 2", "benchmark: a", "benchmark: b"]
+        );
+    }
+
+    #[test]
+    fn combining_file_filter_with_other_filters() {
+        let events = Arc::<Mutex<Vec<_>>>::default();
+        let args = [
+            "bench",
+            "--print",
+            "--quiet",
+            "--filter-file=benches/other.rs",
+            "fib",
+        ];
+        let mut bencher = Bencher::with_args(args.map(str::to_owned));
+        bencher.add_reporter(RecordingReporter(events.clone()));
+        bencher.bench("fib", || 1 + 1);
+        // The file filter doesn't match, but this is not an error.
+        bencher.try_run().unwrap();
+        assert!(events.lock().unwrap().is_empty());
+
+        let args = [
+            "bench",
+            "--print",
+            "--quiet",
+            "--filter-file=src/bencher.rs",
+            "fib",
+        ];
+        let mut bencher = Bencher::with_args(args.map(str::to_owned));
+        bencher.add_reporter(RecordingReporter(events.clone()));
+        bencher.bench("fib", || 1 + 1).bench("other", || 2 + 2);
+        bencher.try_run().unwrap();
+        assert_eq!(*events.lock().unwrap(), ["benchmark: fib"]);
+
+        let args = ["bench", "--list", "--filter-file=src/bencher.rs", "fibb"];
+        let mut bencher = Bencher::with_args(args.map(str::to_owned));
+        bencher.bench("fib", || 1 + 1);
+        let err = bencher.try_run().unwrap_err();
+        assert_matches!(err, BenchError::NoMatches { .. });
+    }
+
+    #[test]
+    fn running_bencher_without_exiting() {
+        let args = ["bench", "--quiet"].map(str::to_owned);
+        let mut bencher = Bencher::with_args(args);
+        bencher.bench("ok", || 1 + 1);
+        bencher.try_run().unwrap();
+
+        let args = ["bench", "--quiet"].map(str::to_owned);
+        let mut bencher = Bencher::with_args(args);
+        bencher
+            .bench("ok", || 1 + 1)
+            .bench("failing", || panic!("oops"));
+        let err = bencher.try_run().unwrap_err();
+        assert_matches!(err, BenchError::TestFailures);
+        assert_eq!(err.exit_code(), BenchError::TEST_FAILURE_CODE);
+        assert_eq!(err.to_string(), "There were test failures");
+
+        let args = ["bench", "--list", "--exact", "fib(1)"].map(str::to_owned);
+        let mut bencher = Bencher::with_args(args);
+        bencher.bench("fib", || 1 + 1);
+        let err = bencher.try_run().unwrap_err();
+        assert_matches!(
+            &err,
+            BenchError::NoMatches { filter, exact: true } if filter == "fib(1)"
+        );
+        assert_eq!(err.exit_code(), BenchError::USAGE_CODE);
+        let err = err.to_string();
+        assert!(err.contains("consider dropping `--exact`"), "{err}");
+    }
+
+    #[test]
+    fn failing_on_unmatched_filters() {
+        let args = ["bench", "--list", "fibb"].map(str::to_owned);
+        let mut bencher = Bencher::with_args(args);
+        bencher.bench("fib", || 1 + 1);
+        let err = bencher.try_run().unwrap_err();
+        assert_matches!(&err, BenchError::NoMatches { filter, exact: false } if filter == "fibb");
+        assert_eq!(err.to_string(), "No benchmarks match the filter `fibb`");
+
+        let args = ["bench", "--list", "--allow-no-matches", "fibb"].map(str::to_owned);
+        let mut bencher = Bencher::with_args(args);
+        bencher.bench("fib", || 1 + 1);
+        bencher.try_run().unwrap();
+
+        let args = ["bench", "--list", "--filter-tag=kind=slow", "fib"];
+        let mut bencher = Bencher::with_args(args.map(str::to_owned));
+        bencher.bench(BenchmarkId::from("fib").tag("kind", "fast"), || 1 + 1);
+        bencher.bench(BenchmarkId::from("other").tag("kind", "slow"), || 1 + 1);
+        let err = bencher.try_run().unwrap_err();
+        assert_matches!(&err, BenchError::NoTagMatches(tags) if tags.len() == 1);
+        assert_eq!(err.exit_code(), BenchError::USAGE_CODE);
+        let err = err.to_string();
+        assert!(err.contains("`kind=slow`"), "{err}");
+    }
+
+    #[test]
+    fn failing_on_unmatched_hyphenated_exact_filter() {
+        let args = ["bench", "--list", "--exact", "fib-20"].map(str::to_owned);
+        let mut bencher = Bencher::with_args(args);
+        bencher.bench("fib/20", || 1 + 1);
+        let err = bencher.try_run().unwrap_err();
+        assert_matches!(&err, BenchError::NoMatches { filter, exact: true } if filter == "fib-20");
+        assert_eq!(err.exit_code(), BenchError::USAGE_CODE);
+        // Hyphens aren't regex operators, so dropping `--exact` isn't suggested.
+        assert_eq!(
+            err.to_string(),
+            "No benchmarks match the exact filter `fib-20`"
+        );
+    }
+
+    #[test]
+    fn detecting_regex_operators_in_filters() {
+        for filter in ["fib(1)", "fib/.*", "^fib", "fib|other", r"fib\d", "fib[12]"] {
+            assert!(has_regex_operators(filter), "{filter}");
+        }
+        for filter in ["fib/20", "fib-20", "fib#1", "fib&other", "fib~", "fib 20"] {
+            assert!(!has_regex_operators(filter), "{filter}");
+        }
+    }
+
+    #[test]
+    fn recording_filter_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("matches");
+        let path_arg = format!("--matches-file={}", path.display());
+
+        let args = ["bench", "--list", &path_arg, "fibb"].map(str::to_owned);
+        let mut bencher = Bencher::with_args(args);
+        bencher.bench("fib", || 1 + 1);
+        bencher.try_run().unwrap();
+        let args = ["bench", "--list", &path_arg, "fib"].map(str::to_owned);
+        let mut bencher = Bencher::with_args(args);
+        bencher.bench("fib", || 1 + 1);
+        bencher.try_run().unwrap();
+
+        let records = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            records,
+            "unmatched: No benchmarks match the filter `fibb`\nmatched\n"
+        );
+    }
+
+    #[test]
+    fn global_setup_is_performed_once() {
+        let setup_count = Cell::new(0);
+        let args = ["bench", "--quiet"].map(str::to_owned);
+        let mut bencher = Bencher::with_args(args);
+        let values = bencher.global_setup(|| {
+            setup_count.set(setup_count.get() + 1);
+            vec![1_u64; 100]
+        });
+        assert_eq!(setup_count.get(), 0);
+
+        bencher
+            .bench("sum", || values.get().iter().sum::<u64>())
+            .bench("max", || values.get().iter().max().copied());
+        bencher.try_run().unwrap();
+        assert_eq!(setup_count.get(), 1);
+    }
+
+    #[test]
+    fn benchmarking_commands_in_test_mode() {
+        let args = ["bench", "--quiet"].map(str::to_owned);
+        let mut bencher = Bencher::with_args(args);
+        bencher.bench_command("true", &Command::new("true"));
+        bencher.try_run().unwrap();
+
+        let args = ["bench", "--quiet"].map(str::to_owned);
+        let mut bencher = Bencher::with_args(args);
+        bencher.bench_command("false", &Command::new("false"));
+        let err = bencher.try_run().unwrap_err();
+        assert_matches!(err, BenchError::TestFailures);
+    }
+
+    #[test]
+    fn wrapping_external_commands() {
+        let mut runner = mock_runner(
+            &["--cg=valgrind", "--cg=--instr-at-start=no"],
+            BenchConfig::default(),
+        );
+        let mut command = Command::new("gzip");
+        command.arg("data.txt").env("LANG", "C").current_dir("/tmp");
+        let command = ExternalCommand::from(&command);
+        runner.target = BenchTarget::Command(command.clone());
+
+        let wrapped = runner.external_command(&command, "out.cg");
+        assert_eq!(wrapped.get_program(), "valgrind");
+        let args: Vec<_> = wrapped.get_args().collect();
+        let instr_pos = args
+            .iter()
+            .position(|&arg| arg == "--instr-at-start=yes")
+            .unwrap();
+        let program_pos = args.iter().position(|&arg| arg == "gzip").unwrap();
+        assert!(instr_pos < program_pos, "{args:?}");
+        assert_eq!(args[program_pos + 1..], ["data.txt"]);
+        // The output path must be absolute since the command overrides the working dir.
+        let out_path = env::current_dir().unwrap().join("out.cg");
+        let out_arg = format!("--cachegrind-out-file={}", out_path.display());
+        assert!(args.contains(&out_arg.as_ref()), "{args:?}");
+        assert_eq!(wrapped.get_current_dir(), Some(Path::new("/tmp")));
+        let envs: Vec<_> = wrapped.get_envs().collect();
+        assert!(
+            envs.contains(&("LANG".as_ref(), Some("C".as_ref()))),
+            "{envs:?}"
+        );
+    }
+
+    #[test]
+    fn async_benchmark_in_test_mode() {
+        struct NoOpWaker;
+
+        impl Wake for NoOpWaker {
+            fn wake(self: Arc<Self>) {
+                // do nothing
+            }
+        }
+
+        let args = ["bench", "--quiet"].map(str::to_owned);
+        let mut bencher = Bencher::with_args(args);
+        let mut outputs = vec![];
+        bencher.bench_async(
+            "async",
+            || async { crate::black_box(21_u64) * 2 },
+            |future| {
+                // The future completes on the first poll, so a no-op waker suffices.
+                let waker = Arc::new(NoOpWaker).into();
+                let poll = pin::pin!(future).poll(&mut Context::from_waker(&waker));
+                let Poll::Ready(output) = poll else {
+                    panic!("future is not ready");
+                };
+                outputs.push(output);
+                output
+            },
+        );
+        assert_eq!(outputs, [42]);
+    }
+
+    #[test]
+    fn benchmark_over_borrowed_input_in_test_mode() {
+        let args = ["bench", "--quiet"].map(str::to_owned);
+        let mut bencher = Bencher::with_args(args);
+        let input = vec![1_u64; 100];
+        let mut outputs = vec![];
+        bencher.bench_refs("sum", &input, |values| {
+            let sum = values.iter().sum::<u64>();
+            outputs.push(sum);
+            sum
+        });
+        bencher.bench_refs("len", input.as_slice(), <[_]>::len);
+        assert_eq!(outputs, [100]);
+        // The input is still owned by the caller.
+        assert_eq!(input.len(), 100);
+    }
+
+    #[test]
+    fn formatting_ids() {
+        let mut call_count = 0;
+        let args = ["bench", "--quiet", "--exact", "renamed/counted"].map(str::to_owned);
+        let mut bencher = Bencher::with_args(args);
+        bencher
+            .bench("counted", || unreachable!())
+            .with_id_formatter(|id| format!("renamed/{id}"))
+            .bench("counted", || call_count += 1)
+            .bench("skipped", || unreachable!());
+        assert_eq!(call_count, 1);
+    }
+
+    #[test]
+    fn capture_mode_in_test_mode() {
+        let args = ["bench", "--quiet"].map(str::to_owned);
+        let mut bencher = Bencher::with_args(args);
+        let mut mode = None;
+        bencher.bench_with_capture("mode", |capture| {
+            mode = Some(capture.mode());
+            capture.measure(|| ());
+        });
+        assert_eq!(mode, Some(BenchMode::Test));
+    }
+
+    #[test]
+    fn estimating_iterations_without_warm_up() {
+        let runner = mock_runner(&["--warm-up=0"], BenchConfig::default());
+        assert_eq!(runner.estimate_iterations(0), 1);
+        assert_eq!(runner.estimate_iterations(1), 1);
+        assert_eq!(runner.estimate_iterations(1_000), 1);
+
+        let config = BenchConfig::default().warm_up(0);
+        let runner = mock_runner(&[], config);
+        assert_eq!(runner.estimate_iterations(1), 1);
+    }
+
+    #[test]
+    fn estimating_iterations_with_warm_up_runs() {
+        let runner = mock_runner(
+            &["--warm-up=100000", "--warmup-runs=5"],
+            BenchConfig::default(),
+        );
+        assert_eq!(runner.estimate_iterations(1_000), 105);
+        assert_eq!(runner.estimate_iterations(10), 1_005); // runs aren't clamped by `max_iterations`
+        assert!(runner.under_warmed_warning(10, 1_005).is_some());
+
+        let runner = mock_runner(&["--warm-up=0", "--warmup-runs=3"], BenchConfig::default());
+        assert_eq!(runner.estimate_iterations(1_000), 4);
+    }
+}
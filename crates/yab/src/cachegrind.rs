@@ -3,10 +3,11 @@
 use std::{
     borrow::Cow,
     collections::HashMap,
+    ffi::OsString,
     fmt, fs, io,
-    io::BufRead,
-    ops,
-    path::Path,
+    io::{BufRead, Read},
+    mem, ops,
+    path::{Path, PathBuf},
     process,
     process::{Command, ExitStatus},
 };
@@ -14,10 +15,38 @@ use std::{
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-use crate::{options::CachegrindOptions, BenchmarkId};
+use crate::{options::CachegrindOptions, BenchMode, BenchmarkId};
 
+/// Valgrind tool used to collect benchmark stats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum Tool {
+    /// `cachegrind`: instruction counts and (optionally) cache simulation.
+    Cachegrind,
+    /// DHAT: instruction counts and heap allocation stats.
+    Dhat,
+}
+
+impl Tool {
+    /// Returns the file extension for raw outputs produced by the tool.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Cachegrind => "cachegrind",
+            Self::Dhat => "dhat",
+        }
+    }
+
+    /// Returns the command-line arg prefix specifying the output file for the tool.
+    pub fn out_file_arg(self) -> &'static str {
+        match self {
+            Self::Cachegrind => "--cachegrind-out-file",
+            Self::Dhat => "--dhat-out-file",
+        }
+    }
+}
+
+/// Abnormal termination of a program spawned by the bencher, together with its captured output.
 #[derive(Debug)]
-pub(crate) struct ExecFailure {
+pub struct ExecFailure {
     status: ExitStatus,
     stdout: String,
     stderr: String,
@@ -31,6 +60,21 @@ impl ExecFailure {
             stderr: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
         }
     }
+
+    /// Returns the exit status of the program.
+    pub fn status(&self) -> ExitStatus {
+        self.status
+    }
+
+    /// Returns the captured stdout of the program (empty if the output was not captured).
+    pub fn stdout(&self) -> &str {
+        &self.stdout
+    }
+
+    /// Returns the captured stderr of the program (empty if the output was not captured).
+    pub fn stderr(&self) -> &str {
+        &self.stderr
+    }
 }
 
 impl fmt::Display for ExecFailure {
@@ -48,37 +92,87 @@ impl fmt::Display for ExecFailure {
 
 impl std::error::Error for ExecFailure {}
 
+/// Error running `cachegrind` (or another Valgrind tool) or processing its outputs.
 #[derive(Debug, thiserror::Error)]
-pub(crate) enum CachegrindError {
+#[non_exhaustive]
+pub enum CachegrindError {
+    /// I/O error spawning or communicating with a program.
     #[error("I/O error executing cachegrind: {0}")]
     Exec(#[source] io::Error),
+    /// Program (e.g., `valgrind` or the first arg of the cachegrind wrapper) was not found.
+    #[error("Program `{program}` was not found. {}", not_found_hint(.program))]
+    ProgramNotFound {
+        /// Name of or path to the program.
+        program: String,
+    },
+    /// Program has exited abnormally.
     #[error("cachegrind exited abnormally: {0}")]
     ExecFailure(#[from] ExecFailure),
+    /// `valgrind --tool=cachegrind --version` has exited abnormally.
     #[error(
-        "Unable to get `cachegrind` version. Please make sure that `valgrind` is installed \
-         and is on PATH"
+        "Unable to get `cachegrind` version: `valgrind --tool=cachegrind --version` exited abnormally. \
+         Please make sure that `valgrind` is installed together with the `cachegrind` tool"
     )]
     NoCachegrind,
+    /// `cachegrind` version cannot be parsed. This is not a fatal error; the bencher reports it as a warning.
+    #[error("Failed parsing `cachegrind` version `{0}`")]
+    Version(String),
 
+    /// I/O error creating a directory for outputs.
     #[error("I/O error creating output directory `{path}`: {error}")]
     CreateOutputDir {
+        /// Path to the directory.
         path: String,
+        /// Underlying I/O error.
         #[source]
         error: io::Error,
     },
+    /// I/O error reading an output.
     #[error("I/O error reading cachegrind output at `{out_path}`: {error}")]
     Read {
+        /// Path to the output.
         out_path: String,
+        /// Underlying I/O error.
         #[source]
         error: io::Error,
     },
+    /// Error parsing an output.
     #[error("Failed parsing cachegrind output at `{out_path}`: {message}")]
     Parse {
+        /// Path to the output.
         out_path: String,
+        /// Human-readable error message.
         message: Cow<'static, str>,
     },
 }
 
+fn not_found_hint(program: &str) -> &'static str {
+    match program {
+        "valgrind" => "Please make sure that `valgrind` is installed and is on PATH",
+        "setarch" => {
+            "`setarch` is used by the default cachegrind wrapper to disable ASLR; please make sure that it is installed \
+             and is on PATH (e.g., via `util-linux` package), or customize the wrapper via `CACHEGRIND_WRAPPER`"
+        }
+        "taskset" => {
+            "`taskset` is used to pin benchmarks to a CPU core via `--cpu-affinity`; please make sure that it is \
+             installed and is on PATH (e.g., via `util-linux` package)"
+        }
+        _ => "Please check the cachegrind wrapper specified via `--cg` / `CACHEGRIND_WRAPPER`",
+    }
+}
+
+impl CachegrindError {
+    /// Converts an error spawning `command`, distinguishing the case when the spawned program is not found.
+    fn spawn(command: &Command, error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::NotFound {
+            let program = command.get_program().to_string_lossy().into_owned();
+            Self::ProgramNotFound { program }
+        } else {
+            Self::Exec(error)
+        }
+    }
+}
+
 #[derive(Debug)]
 enum ParseError {
     Custom(Cow<'static, str>),
@@ -112,41 +206,155 @@ impl From<String> for ParseError {
     }
 }
 
-pub(crate) fn check() -> Result<String, CachegrindError> {
-    let output = Command::new("valgrind")
-        .args(["--tool=cachegrind", "--version"])
+/// Parsed `cachegrind` version, e.g. `3.23.0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct CachegrindVersion(pub u32, pub u32, pub u32);
+
+impl fmt::Display for CachegrindVersion {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self(major, minor, patch) = self;
+        write!(formatter, "{major}.{minor}.{patch}")
+    }
+}
+
+impl CachegrindVersion {
+    /// Minimum version supporting `CACHEGRIND_{START|STOP}_INSTRUMENTATION` client requests.
+    pub const INSTRUMENTATION: Self = Self(3, 22, 0);
+
+    /// Parses the version from the `valgrind --version` output, such as `valgrind-3.23.0`.
+    /// Vendor suffixes (e.g., `valgrind-3.22.0.GIT` or `valgrind-3.19.0-Debian`) are ignored.
+    fn parse(raw: &str) -> Option<Self> {
+        let version = raw.strip_prefix("valgrind-").unwrap_or(raw);
+        let mut parts = version.split('.').map(|part| {
+            let digits_len = part
+                .find(|ch: char| !ch.is_ascii_digit())
+                .unwrap_or(part.len());
+            part[..digits_len].parse::<u32>().ok()
+        });
+        let major = parts.next()??;
+        let minor = parts.next()??;
+        let patch = parts.next().flatten().unwrap_or(0);
+        Some(Self(major, minor, patch))
+    }
+}
+
+pub(crate) fn check() -> Result<CachegrindVersion, CachegrindError> {
+    let mut command = Command::new("valgrind");
+    command.args(["--tool=cachegrind", "--version"]);
+    let output = command
         .output()
-        .map_err(CachegrindError::Exec)?;
+        .map_err(|err| CachegrindError::spawn(&command, err))?;
     if !output.status.success() {
         return Err(CachegrindError::NoCachegrind);
     }
-    let version = String::from_utf8(output.stdout).map_err(|err| {
-        let err = io::Error::new(io::ErrorKind::Other, err);
-        CachegrindError::Exec(err)
-    })?;
-    Ok(version.trim().to_owned())
+    let version = String::from_utf8(output.stdout)
+        .map_err(|err| CachegrindError::Exec(io::Error::other(err)))?;
+    let version = version.trim();
+    CachegrindVersion::parse(version).ok_or_else(|| CachegrindError::Version(version.to_owned()))
 }
 
 #[derive(Debug)]
 pub(crate) struct SpawnArgs<'a> {
+    pub tool: Tool,
     pub command: Command,
     pub out_path: &'a str,
     pub this_executable: &'a str,
     pub id: &'a BenchmarkId,
     pub iterations: u64,
     pub is_baseline: bool,
+    pub seed: u64,
+    /// Whether to inherit stdout / stderr instead of capturing them.
+    pub show_output: bool,
+}
+
+impl SpawnArgs<'_> {
+    /// Creates the command spawning the benchmark executable under `cachegrind`.
+    pub(crate) fn into_command(self) -> Command {
+        let mut command = self.command;
+        command.arg(self.this_executable);
+        let options = CachegrindOptions {
+            iterations: self.iterations,
+            is_baseline: self.is_baseline,
+            id: self.id.to_string(),
+            seed: self.seed,
+        };
+        options.push_args(&mut command);
+        command
+    }
+}
+
+/// External command benchmarked via [`Bencher::bench_command()`](crate::Bencher::bench_command()).
+/// Only the program, args, env vars and the working directory of the original command are retained.
+#[derive(Debug, Clone)]
+pub(crate) struct ExternalCommand {
+    program: OsString,
+    args: Vec<OsString>,
+    envs: Vec<(OsString, Option<OsString>)>,
+    current_dir: Option<PathBuf>,
+}
+
+impl From<&Command> for ExternalCommand {
+    fn from(command: &Command) -> Self {
+        Self {
+            program: command.get_program().to_owned(),
+            args: command.get_args().map(ToOwned::to_owned).collect(),
+            envs: command
+                .get_envs()
+                .map(|(key, value)| (key.to_owned(), value.map(ToOwned::to_owned)))
+                .collect(),
+            current_dir: command.get_current_dir().map(ToOwned::to_owned),
+        }
+    }
+}
+
+impl ExternalCommand {
+    /// Creates a command running the program directly, without `cachegrind`.
+    pub(crate) fn to_command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        self.configure(&mut command);
+        command
+    }
+
+    /// Checks whether the command overrides the working directory.
+    pub(crate) fn has_current_dir(&self) -> bool {
+        self.current_dir.is_some()
+    }
+
+    /// Appends the program to the `cachegrind` wrapper command.
+    pub(crate) fn append_to(&self, mut wrapper: Command) -> Command {
+        wrapper.arg(&self.program);
+        self.configure(&mut wrapper);
+        wrapper
+    }
+
+    fn configure(&self, command: &mut Command) {
+        command.args(&self.args);
+        for (key, value) in &self.envs {
+            if let Some(value) = value {
+                command.env(key, value);
+            } else {
+                command.env_remove(key);
+            }
+        }
+        if let Some(dir) = &self.current_dir {
+            command.current_dir(dir);
+        }
+    }
 }
 
 pub(crate) fn spawn_instrumented(args: SpawnArgs) -> Result<CachegrindStats, CachegrindError> {
-    let SpawnArgs {
-        mut command,
-        out_path,
-        this_executable,
-        id,
-        iterations,
-        is_baseline,
-    } = args;
+    let (tool, out_path, show_output) = (args.tool, args.out_path, args.show_output);
+    spawn(args.into_command(), tool, out_path, show_output)
+}
 
+/// Spawns the specified `command` (the `cachegrind` wrapper with the benchmarked program) and parses
+/// the output produced at `out_path`.
+pub(crate) fn spawn(
+    mut command: Command,
+    tool: Tool,
+    out_path: &str,
+    show_output: bool,
+) -> Result<CachegrindStats, CachegrindError> {
     if let Some(parent_dir) = Path::new(out_path).parent() {
         fs::create_dir_all(parent_dir).map_err(|error| CachegrindError::CreateOutputDir {
             path: parent_dir.display().to_string(),
@@ -154,15 +362,16 @@ pub(crate) fn spawn_instrumented(args: SpawnArgs) -> Result<CachegrindStats, Cac
         })?;
     }
 
-    command.arg(this_executable);
-    let options = CachegrindOptions {
-        iterations,
-        is_baseline,
-        id: id.to_string(),
+    let output = if show_output {
+        command.status().map(|status| process::Output {
+            status,
+            stdout: vec![],
+            stderr: vec![],
+        })
+    } else {
+        command.output()
     };
-    options.push_args(&mut command);
-
-    let output = command.output().map_err(CachegrindError::Exec)?;
+    let output = output.map_err(|err| CachegrindError::spawn(&command, err))?;
     if !output.status.success() {
         return Err(ExecFailure::new(&output).into());
     }
@@ -171,8 +380,7 @@ pub(crate) fn spawn_instrumented(args: SpawnArgs) -> Result<CachegrindStats, Cac
         out_path: out_path.to_owned(),
         error,
     })?;
-    CachegrindStats::read(io::BufReader::new(out))
-        .map_err(|err| err.generalize(out_path.to_owned()))
+    CachegrindStats::new(out, out_path, tool)
 }
 
 /// Information about a particular type of operations (instruction reads, data reads / writes).
@@ -304,17 +512,71 @@ impl ops::Mul<u64> for FullCachegrindStats {
         Self {
             instructions: self.instructions * rhs,
             data_reads: self.data_reads * rhs,
-            data_writes: self.data_reads * rhs,
+            data_writes: self.data_writes * rhs,
+        }
+    }
+}
+
+/// Heap allocation stats collected by DHAT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HeapStats {
+    /// Total number of heap allocations.
+    pub allocations: u64,
+    /// Total number of allocated bytes.
+    pub bytes: u64,
+}
+
+/// Uses saturated subtraction for all primitive `u64` values.
+impl ops::Sub for HeapStats {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            allocations: self.allocations.saturating_sub(rhs.allocations),
+            bytes: self.bytes.saturating_sub(rhs.bytes),
         }
     }
 }
 
-/// Raw summary output produced by `cachegrind`.
+/// Subset of the DHAT output (in the JSON format) necessary to compute [`CachegrindStats`].
+#[cfg(feature = "serde")]
+#[derive(Debug, Deserialize)]
+struct DhatOutput {
+    #[serde(rename = "dhatFileVersion")]
+    version: u64,
+    mode: Option<String>,
+    #[serde(rename = "te")]
+    total_instructions: Option<u64>,
+    #[serde(rename = "pps", default)]
+    program_points: Vec<DhatProgramPoint>,
+}
+
+/// Program point (i.e., allocation site) in the DHAT output.
+#[cfg(feature = "serde")]
+#[derive(Debug, Deserialize)]
+struct DhatProgramPoint {
+    #[serde(rename = "tb")]
+    total_bytes: u64,
+    #[serde(rename = "tbk")]
+    total_blocks: u64,
+}
+
+/// Raw summary output produced by `cachegrind` (or DHAT, if it is used as the collector).
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(untagged))]
 #[non_exhaustive]
 pub enum CachegrindStats {
+    /// Stats produced by DHAT (enabled via `--tool=dhat` command-line arg).
+    // Must be declared before `Simple` so that untagged deserialization doesn't lose heap stats.
+    #[non_exhaustive]
+    Heap {
+        /// Total number of executed instructions.
+        instructions: u64,
+        /// Heap allocation stats.
+        heap: HeapStats,
+    },
     /// Stats produced by `cachegrind` with disabled cache simulation.
     #[non_exhaustive]
     Simple {
@@ -322,16 +584,52 @@ pub enum CachegrindStats {
         instructions: u64,
     },
     /// Full stats including cache simulation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use yab::{CachegrindDataPoint, CachegrindStats, FullCachegrindStats};
+    ///
+    /// let point = |total| CachegrindDataPoint { total, l1_misses: 0, l3_misses: 0 };
+    /// let stats = CachegrindStats::Full(FullCachegrindStats {
+    ///     instructions: point(1_000),
+    ///     data_reads: point(200),
+    ///     data_writes: point(100),
+    /// });
+    /// assert_eq!(stats.total_instructions(), 1_000);
+    /// ```
     Full(FullCachegrindStats),
 }
 
+/// Error returned by [`CachegrindStats::try_add()`] and [`CachegrindStats::try_sub()`] if the stats
+/// have different variants.
+#[derive(Debug, thiserror::Error)]
+#[error("Cannot combine stats produced by {lhs} and {rhs}")]
+pub struct IncompatibleStatsError {
+    lhs: &'static str,
+    rhs: &'static str,
+}
+
 /// Uses saturated subtraction for all primitive `u64` values.
+///
+/// If the stats have different variants, they are downgraded to [`Self::Simple`], i.e., cache / heap data
+/// is silently discarded. Use [`Self::try_sub()`] to treat this as an error instead.
 impl ops::Sub for CachegrindStats {
     type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
             (Self::Full(lhs), Self::Full(rhs)) => Self::Full(lhs - rhs),
+            (
+                Self::Heap { instructions, heap },
+                Self::Heap {
+                    instructions: rhs_instructions,
+                    heap: rhs_heap,
+                },
+            ) => Self::Heap {
+                instructions: instructions.saturating_sub(rhs_instructions),
+                heap: heap - rhs_heap,
+            },
             _ => Self::Simple {
                 instructions: self
                     .total_instructions()
@@ -342,9 +640,46 @@ impl ops::Sub for CachegrindStats {
 }
 
 impl CachegrindStats {
-    pub(crate) fn new(file: fs::File, path: &str) -> Result<Self, CachegrindError> {
+    pub(crate) fn new(file: fs::File, path: &str, tool: Tool) -> Result<Self, CachegrindError> {
         let reader = io::BufReader::new(file);
-        Self::read(reader).map_err(|err| err.generalize(path.to_owned()))
+        let result = match tool {
+            Tool::Cachegrind => Self::read(reader),
+            Tool::Dhat => Self::read_dhat(reader),
+        };
+        result.map_err(|err| err.generalize(path.to_owned()))
+    }
+
+    /// Reads DHAT output. Heap stats are summed over all program points (i.e., allocation sites) and thus
+    /// cover the entire process; DHAT doesn't support capturing a part of the execution.
+    #[cfg(feature = "serde")]
+    fn read_dhat(reader: impl Read) -> Result<Self, ParseError> {
+        let output: DhatOutput = serde_json::from_reader(reader)
+            .map_err(|err| format!("failed parsing DHAT output: {err}"))?;
+        if output.version != 2 {
+            return Err("unsupported DHAT output version; expected 2".into());
+        }
+        if output.mode.as_deref() != Some("heap") {
+            return Err("unsupported DHAT mode; expected `heap`".into());
+        }
+        let instructions = output
+            .total_instructions
+            .ok_or("no total instructions (`te`) in DHAT output")?;
+
+        let mut heap = HeapStats {
+            allocations: 0,
+            bytes: 0,
+        };
+        for point in &output.program_points {
+            heap.bytes += point.total_bytes;
+            heap.allocations += point.total_blocks;
+        }
+        Ok(Self::Heap { instructions, heap })
+    }
+
+    // `--tool=dhat` is rejected on validation without the `serde` feature, so this method shouldn't be called.
+    #[cfg(not(feature = "serde"))]
+    fn read_dhat(_reader: impl Read) -> Result<Self, ParseError> {
+        Err("parsing DHAT output requires the `serde` crate feature".into())
     }
 
     fn read(reader: impl BufRead) -> Result<Self, ParseError> {
@@ -376,7 +711,13 @@ impl CachegrindStats {
             })
             .collect::<Result<_, _>>()?;
         if events.len() != summary.len() {
-            return Err("mismatch between events and summary".into());
+            let message = format!(
+                "mismatch between events and summary: expected {} columns ({}), got {}",
+                events.len(),
+                events.join(" "),
+                summary.len()
+            );
+            return Err(message.into());
         }
 
         let summary_by_event: HashMap<_, _> = events.into_iter().zip(summary).collect();
@@ -388,25 +729,125 @@ impl CachegrindStats {
         })
     }
 
+    /// Creates stats with only the total number of executed instructions, as produced by `cachegrind`
+    /// with disabled cache simulation. Useful e.g. to test custom [reporters](crate::reporter).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use yab::CachegrindStats;
+    ///
+    /// let stats = CachegrindStats::simple(1_000);
+    /// assert_eq!(stats.total_instructions(), 1_000);
+    /// assert!(stats.as_full().is_none());
+    /// ```
+    pub const fn simple(instructions: u64) -> Self {
+        Self::Simple { instructions }
+    }
+
+    /// Creates stats with heap allocation info, as produced by DHAT.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use yab::{CachegrindStats, HeapStats};
+    ///
+    /// let heap = HeapStats { allocations: 3, bytes: 128 };
+    /// let stats = CachegrindStats::with_heap(1_000, heap);
+    /// assert_eq!(stats.total_instructions(), 1_000);
+    /// assert_eq!(stats.heap(), Some(&heap));
+    /// ```
+    pub const fn with_heap(instructions: u64, heap: HeapStats) -> Self {
+        Self::Heap { instructions, heap }
+    }
+
     /// Returns full stats if they are available.
     pub fn as_full(&self) -> Option<&FullCachegrindStats> {
         match self {
             Self::Full(stats) => Some(stats),
-            Self::Simple { .. } => None,
+            Self::Simple { .. } | Self::Heap { .. } => None,
+        }
+    }
+
+    /// Returns heap allocation stats if they are available.
+    pub fn heap(&self) -> Option<&HeapStats> {
+        match self {
+            Self::Heap { heap, .. } => Some(heap),
+            Self::Simple { .. } | Self::Full(_) => None,
         }
     }
 
     /// Gets the total number of executed instructions.
     pub fn total_instructions(&self) -> u64 {
         match self {
-            Self::Simple { instructions } => *instructions,
+            Self::Simple { instructions } | Self::Heap { instructions, .. } => *instructions,
             Self::Full(stats) => stats.instructions.total,
         }
     }
+
+    /// Adds stats of the same variant.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stats have different variants. Unlike this method, the lossy `Add`
+    /// would have to discard cache / heap data in this case.
+    pub fn try_add(self, rhs: Self) -> Result<Self, IncompatibleStatsError> {
+        Ok(match (self, rhs) {
+            (Self::Full(lhs), Self::Full(rhs)) => Self::Full(lhs + rhs),
+            (
+                Self::Heap { instructions, heap },
+                Self::Heap {
+                    instructions: rhs_instructions,
+                    heap: rhs_heap,
+                },
+            ) => Self::Heap {
+                instructions: instructions + rhs_instructions,
+                heap: HeapStats {
+                    allocations: heap.allocations + rhs_heap.allocations,
+                    bytes: heap.bytes + rhs_heap.bytes,
+                },
+            },
+            (Self::Simple { instructions }, Self::Simple { .. }) => Self::Simple {
+                instructions: instructions + rhs.total_instructions(),
+            },
+            _ => return Err(self.incompatible_with(&rhs)),
+        })
+    }
+
+    /// Subtracts stats of the same variant using saturated subtraction for all primitive `u64` values.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stats have different variants. In this case, the `Sub` implementation
+    /// would silently downgrade the result to [`Self::Simple`].
+    pub fn try_sub(self, rhs: Self) -> Result<Self, IncompatibleStatsError> {
+        if mem::discriminant(&self) == mem::discriminant(&rhs) {
+            Ok(self - rhs)
+        } else {
+            Err(self.incompatible_with(&rhs))
+        }
+    }
+
+    fn incompatible_with(&self, rhs: &Self) -> IncompatibleStatsError {
+        IncompatibleStatsError {
+            lhs: self.collector(),
+            rhs: rhs.collector(),
+        }
+    }
+
+    /// Returns a human-readable description of the collector configuration that has produced these stats.
+    pub(crate) fn collector(&self) -> &'static str {
+        match self {
+            Self::Simple { .. } => "cachegrind w/o cache simulation",
+            Self::Full(_) => "cachegrind with cache simulation",
+            Self::Heap { .. } => "DHAT",
+        }
+    }
 }
 
 /// High-level memory access stats summarized from [`CachegrindStats`].
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[non_exhaustive]
 pub struct AccessSummary {
     /// Total number of instructions executed.
@@ -420,6 +861,23 @@ pub struct AccessSummary {
 }
 
 impl AccessSummary {
+    /// Creates a summary for stats collected without cache simulation. All instructions are assumed to hit
+    /// the L1 cache, and there are no data accesses, so that [estimated cycles](Self::estimated_cycles())
+    /// are equal to the number of instructions.
+    ///
+    /// Note that `l1_hits` is thus not a measured value. It is set to the number of instructions rather than zero
+    /// because the instruction count is the best available cycle estimate without cache simulation,
+    /// and zero cache hits would estimate zero cycles. Use [`CachegrindStats::as_full()`] to distinguish
+    /// simulated cache stats; the built-in reporters only output cache rows for such stats.
+    pub fn from_simple(instructions: u64) -> Self {
+        Self {
+            instructions,
+            l1_hits: instructions,
+            l3_hits: 0,
+            ram_accesses: 0,
+        }
+    }
+
     /// Returns the estimated number of CPU cycles using Itamar Turner-Trauring's [formula].
     ///
     /// [formula]: https://pythonspeed.com/articles/consistent-benchmarking-in-ci/
@@ -447,6 +905,16 @@ impl From<FullCachegrindStats> for AccessSummary {
     }
 }
 
+/// Uses [`AccessSummary::from_simple()`] for stats collected without cache simulation.
+impl From<CachegrindStats> for AccessSummary {
+    fn from(stats: CachegrindStats) -> Self {
+        match stats {
+            CachegrindStats::Full(full) => full.into(),
+            _ => Self::from_simple(stats.total_instructions()),
+        }
+    }
+}
+
 pub(crate) fn run_instrumented<T>(
     mut bench: impl FnMut(Capture) -> T,
     iterations: u64,
@@ -464,6 +932,7 @@ pub(crate) fn run_instrumented<T>(
                 (true, true) => CaptureBehavior::TerminateOnStart,
                 (true, false) => CaptureBehavior::TerminateOnEnd,
             }),
+            mode: BenchMode::Bench,
         };
         outputs.push(crate::black_box(bench(instrumentation)));
     }
@@ -487,12 +956,76 @@ enum CaptureBehavior {
 #[must_use = "should be `start`ed"]
 pub struct Capture {
     behavior: CaptureBehavior,
+    mode: BenchMode,
 }
 
 impl Capture {
+    /// Creates a capture for the test mode.
     pub(crate) const fn no_op() -> Self {
         Self {
             behavior: CaptureBehavior::NoOp,
+            mode: BenchMode::Test,
+        }
+    }
+
+    /// Returns the mode the benchmark is executing in; either [`BenchMode::Test`] or [`BenchMode::Bench`].
+    /// This can be used to make setup cheaper when testing benchmarks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use yab::{black_box, BenchMode, Bencher};
+    ///
+    /// fn benchmarks(bencher: &mut Bencher) {
+    ///     bencher.bench_with_capture("sum", |capture| {
+    ///         let len = if capture.mode() == BenchMode::Test { 10 } else { 1_000_000 };
+    ///         let values: Vec<u64> = (0..len).collect();
+    ///         capture.measure(|| black_box(&values).iter().sum::<u64>());
+    ///     });
+    /// }
+    /// # yab::main!(benchmarks);
+    /// ```
+    pub fn mode(&self) -> BenchMode {
+        self.mode
+    }
+
+    /// Touches the provided `data` so that it is loaded into (simulated) caches before capturing starts.
+    /// This makes stats for the captured region less dependent on how the data was accessed during setup.
+    /// Data is touched once per cache line.
+    ///
+    /// # Limitations
+    ///
+    /// - `cachegrind` simulates caches, but not page faults or TLB misses, so prefaulting only affects
+    ///   simulated cache hits and misses.
+    /// - Only the last touched part of `data` that fits into the simulated caches will be warm;
+    ///   data larger than the last-level cache (8 MiB with default `cachegrind` options) will still
+    ///   produce RAM accesses when accessed in the captured region.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use yab::{black_box, Bencher};
+    ///
+    /// fn benchmarks(bencher: &mut Bencher) {
+    ///     bencher.bench_with_capture("sum", |capture| {
+    ///         let values: Vec<u64> = (0..100_000).collect();
+    ///         capture.prefault(&values);
+    ///         capture.measure(|| black_box(&values).iter().sum::<u64>());
+    ///     });
+    /// }
+    /// # yab::main!(benchmarks);
+    /// ```
+    #[inline(never)]
+    pub fn prefault<T: Copy>(&self, data: &[T]) {
+        const CACHE_LINE_SIZE: usize = 64;
+
+        let item_size = mem::size_of::<T>();
+        if item_size == 0 {
+            return;
+        }
+        let stride = (CACHE_LINE_SIZE / item_size).max(1);
+        for item in data.iter().step_by(stride) {
+            crate::black_box(*item);
         }
     }
 
@@ -541,6 +1074,25 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn parsing_cachegrind_version() {
+        let version = CachegrindVersion::parse("valgrind-3.23.0").unwrap();
+        assert_eq!(version, CachegrindVersion(3, 23, 0));
+        assert_eq!(version.to_string(), "3.23.0");
+        assert!(version >= CachegrindVersion::INSTRUMENTATION);
+
+        let version = CachegrindVersion::parse("valgrind-3.22.0.GIT").unwrap();
+        assert_eq!(version, CachegrindVersion(3, 22, 0));
+        let version = CachegrindVersion::parse("valgrind-3.19.0-Debian").unwrap();
+        assert_eq!(version, CachegrindVersion(3, 19, 0));
+        assert!(version < CachegrindVersion::INSTRUMENTATION);
+        let version = CachegrindVersion::parse("valgrind-3.21").unwrap();
+        assert_eq!(version, CachegrindVersion(3, 21, 0));
+
+        assert_eq!(CachegrindVersion::parse("valgrind"), None);
+        assert_eq!(CachegrindVersion::parse("valgrind-three"), None);
+    }
+
     #[test]
     fn parsing_basic_cachegrind_output() {
         let output = "\
@@ -553,6 +1105,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn reporting_missing_programs() {
+        let not_found = || io::Error::from(io::ErrorKind::NotFound);
+        let err = CachegrindError::spawn(&Command::new("setarch"), not_found());
+        assert_matches!(&err, CachegrindError::ProgramNotFound { program } if program == "setarch");
+        let err = err.to_string();
+        assert!(err.starts_with("Program `setarch` was not found"), "{err}");
+        assert!(err.contains("disable ASLR"), "{err}");
+
+        let err = CachegrindError::spawn(&Command::new("valgrind"), not_found()).to_string();
+        assert!(err.contains("`valgrind` is installed"), "{err}");
+
+        let err = CachegrindError::spawn(&Command::new("custom-wrapper"), not_found()).to_string();
+        assert!(err.contains("CACHEGRIND_WRAPPER"), "{err}");
+
+        let err = io::Error::from(io::ErrorKind::PermissionDenied);
+        let err = CachegrindError::spawn(&Command::new("setarch"), err);
+        assert_matches!(err, CachegrindError::Exec(_));
+    }
+
+    #[test]
+    fn parsing_cachegrind_output_with_column_mismatch() {
+        let output = "\
+            events: Ir I1mr ILmr\n\
+            summary: 1234 12";
+        let err = CachegrindStats::read(output.as_bytes()).unwrap_err();
+        assert_matches!(
+            err,
+            ParseError::Custom(msg) if msg.contains("expected 3 columns (Ir I1mr ILmr), got 2")
+        );
+    }
+
     #[test]
     fn parsing_full_cachegrind_output() {
         let output = "\
@@ -579,6 +1163,169 @@ mod tests {
         assert_eq!(stats.data_writes.l3_misses, 1_210);
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn parsing_dhat_output() {
+        let output = r#"{"dhatFileVersion":2
+,"mode":"heap"
+,"verb":"Allocated"
+,"bklt":true
+,"bkacc":true
+,"tu":"instrs"
+,"Mtu":"instr"
+,"tuth":500
+,"cmd":"target/release/deps/all-1234 --cachegrind-instrument 2 - fib_short"
+,"pid":1234
+,"te":265311
+,"tg":262150
+,"pps":
+[{"tb":1024,"tbk":1,"tl":0
+ ,"mb":1024,"mbk":1
+ ,"fs":[1,2]
+ }
+,{"tb":36,"tbk":3,"tl":0
+ ,"mb":24,"mbk":2
+ ,"fs":[3]
+ }
+]
+,"ftbl":
+["[root]"
+,"0x4C2DB8F: malloc (vg_replace_malloc.c:381)"
+]
+}"#;
+        let stats = CachegrindStats::read_dhat(output.as_bytes()).unwrap();
+        assert_eq!(stats.total_instructions(), 265_311);
+        let heap = stats.heap().unwrap();
+        assert_eq!(heap.allocations, 4);
+        assert_eq!(heap.bytes, 1_060);
+        assert!(stats.as_full().is_none());
+
+        let err = CachegrindStats::read_dhat(r#"{"dhatFileVersion":1}"#.as_bytes()).unwrap_err();
+        assert_matches!(err, ParseError::Custom(msg) if msg.contains("version"));
+        let err = CachegrindStats::read_dhat(r#"{"dhatFileVersion":2"#.as_bytes()).unwrap_err();
+        assert_matches!(err, ParseError::Custom(msg) if msg.contains("failed parsing"));
+        let output = r#"{"dhatFileVersion":2,"mode":"heap","te":10,"pps":[{"tb":"1"}]}"#;
+        let err = CachegrindStats::read_dhat(output.as_bytes()).unwrap_err();
+        assert_matches!(err, ParseError::Custom(msg) if msg.contains("failed parsing"));
+    }
+
+    #[test]
+    fn subtracting_heap_stats() {
+        let full = CachegrindStats::Heap {
+            instructions: 1_000,
+            heap: HeapStats {
+                allocations: 10,
+                bytes: 1_024,
+            },
+        };
+        let baseline = CachegrindStats::Heap {
+            instructions: 300,
+            heap: HeapStats {
+                allocations: 3,
+                bytes: 256,
+            },
+        };
+        let diff = full - baseline;
+        assert_eq!(diff.total_instructions(), 700);
+        assert_eq!(
+            *diff.heap().unwrap(),
+            HeapStats {
+                allocations: 7,
+                bytes: 768
+            }
+        );
+    }
+
+    #[test]
+    fn summarizing_stats() {
+        let summary = AccessSummary::from(CachegrindStats::simple(100));
+        assert_eq!(summary.instructions, 100);
+        assert_eq!(summary.l1_hits, 100);
+        assert_eq!(summary.l3_hits + summary.ram_accesses, 0);
+        assert_eq!(summary.estimated_cycles(), 100);
+
+        let full = FullCachegrindStats {
+            instructions: CachegrindDataPoint {
+                total: 100,
+                l1_misses: 20,
+                l3_misses: 10,
+            },
+            data_reads: CachegrindDataPoint {
+                total: 50,
+                l1_misses: 10,
+                l3_misses: 5,
+            },
+            data_writes: CachegrindDataPoint {
+                total: 10,
+                l1_misses: 0,
+                l3_misses: 0,
+            },
+        };
+        let summary = AccessSummary::from(CachegrindStats::Full(full));
+        assert_eq!(summary.instructions, 100);
+        assert_eq!(summary.l1_hits, 130);
+        assert_eq!(summary.l3_hits, 15);
+        assert_eq!(summary.ram_accesses, 15);
+    }
+
+    #[test]
+    fn multiplying_full_stats() {
+        let point = |total| CachegrindDataPoint {
+            total,
+            l1_misses: total / 10,
+            l3_misses: total / 100,
+        };
+        let stats = FullCachegrindStats {
+            instructions: point(1_000),
+            data_reads: point(500),
+            data_writes: point(200),
+        };
+        let scaled = stats * 3;
+        assert_eq!(scaled.instructions, point(3_000));
+        assert_eq!(scaled.data_reads, point(1_500));
+        assert_eq!(scaled.data_writes, point(600));
+    }
+
+    #[test]
+    fn checked_stats_arithmetic() {
+        let point = |total| CachegrindDataPoint {
+            total,
+            l1_misses: total / 10,
+            l3_misses: total / 100,
+        };
+        let full = CachegrindStats::Full(FullCachegrindStats {
+            instructions: point(1_000),
+            data_reads: point(500),
+            data_writes: point(200),
+        });
+        let sum = full.try_add(full).unwrap();
+        assert_eq!(sum.as_full().unwrap().data_writes, point(400));
+        assert_eq!(sum.try_sub(full).unwrap(), full);
+
+        let simple = CachegrindStats::simple(300);
+        assert_eq!(
+            simple.try_add(simple).unwrap(),
+            CachegrindStats::simple(600)
+        );
+        let heap = HeapStats {
+            allocations: 2,
+            bytes: 100,
+        };
+        let with_heap = CachegrindStats::with_heap(300, heap);
+        let sum = with_heap.try_add(with_heap).unwrap();
+        assert_eq!(sum.heap().unwrap().bytes, 200);
+
+        let err = full.try_sub(simple).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot combine stats produced by cachegrind with cache simulation and \
+             cachegrind w/o cache simulation"
+        );
+        full.try_add(with_heap).unwrap_err();
+        // The lossy implementation downgrades stats.
+        assert_eq!(full - simple, CachegrindStats::simple(700));
+    }
+
     #[cfg(feature = "serde")]
     #[test]
     fn serializing_stats() {
@@ -612,5 +1359,29 @@ mod tests {
         let stats: CachegrindStats = serde_json::from_value(json.clone()).unwrap();
         assert_full_stats(stats.as_full().unwrap());
         assert_eq!(serde_json::to_value(stats).unwrap(), json);
+
+        let json = serde_json::json!({
+            "instructions": 1_234,
+            "heap": { "allocations": 5, "bytes": 1_024 },
+        });
+        let stats: CachegrindStats = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(stats.total_instructions(), 1_234);
+        assert_eq!(stats.heap().unwrap().allocations, 5);
+        assert_eq!(serde_json::to_value(stats).unwrap(), json);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializing_access_summary() {
+        let json = serde_json::json!({
+            "instructions": 1_000,
+            "l1_hits": 1_200,
+            "l3_hits": 30,
+            "ram_accesses": 5,
+        });
+        let summary: AccessSummary = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(summary.l3_hits, 30);
+        assert_eq!(summary.estimated_cycles(), 1_200 + 30 * 5 + 5 * 35);
+        assert_eq!(serde_json::to_value(summary).unwrap(), json);
     }
 }
@@ -0,0 +1,393 @@
+//! History of benchmark results used to compare against an aggregate of several previous runs.
+//! Persisting the history requires the `serde` feature.
+
+use std::{io, mem};
+
+use clap::ValueEnum;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{CachegrindDataPoint, CachegrindStats, FullCachegrindStats, HeapStats};
+
+/// Baseline to compare benchmark results against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub(crate) enum Comparison {
+    /// Compare against the previous run.
+    #[default]
+    Prev,
+    /// Compare against the median entry of the benchmark history, ordered by the number of instructions.
+    /// For an even number of entries, the lower median is used.
+    #[value(name = "history:median")]
+    HistoryMedian,
+    /// Compare against the per-stat mean of the benchmark history.
+    #[value(name = "history:mean")]
+    HistoryMean,
+}
+
+/// Latest benchmark results stored in a JSON file.
+#[derive(Debug, Default)]
+pub(crate) struct History {
+    entries: Vec<Entry>,
+}
+
+/// History entry. Stats are tagged by their variant, so that entries with different kinds of stats
+/// are unambiguous.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Entry {
+    #[cfg_attr(feature = "serde", serde(with = "StatsDef"))]
+    stats: CachegrindStats,
+    /// Marks the entry recorded by the latest benchmark run, i.e., the one corresponding to the persisted
+    /// `cachegrind` outputs.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "is_false"))]
+    is_latest_run: bool,
+}
+
+#[cfg(feature = "serde")]
+#[allow(clippy::trivially_copy_pass_by_ref)] // signature required by `serde`
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "CachegrindStats", tag = "kind", rename_all = "snake_case")]
+enum StatsDef {
+    Simple {
+        instructions: u64,
+    },
+    Heap {
+        instructions: u64,
+        #[serde(with = "HeapStatsDef")]
+        heap: HeapStats,
+    },
+    Full(#[serde(with = "FullStatsDef")] FullCachegrindStats),
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "HeapStats")]
+struct HeapStatsDef {
+    allocations: u64,
+    bytes: u64,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "FullCachegrindStats")]
+struct FullStatsDef {
+    #[serde(with = "DataPointDef")]
+    instructions: CachegrindDataPoint,
+    #[serde(with = "DataPointDef")]
+    data_reads: CachegrindDataPoint,
+    #[serde(with = "DataPointDef")]
+    data_writes: CachegrindDataPoint,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "CachegrindDataPoint")]
+struct DataPointDef {
+    total: u64,
+    l1_misses: u64,
+    l3_misses: u64,
+}
+
+impl History {
+    /// Loads the history from the specified path. A missing file is treated as an empty history.
+    #[cfg(feature = "serde")]
+    pub fn load(path: &str) -> io::Result<Self> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err),
+        };
+        let entries = serde_json::from_str(&contents).map_err(|err| {
+            let message = format!("failed parsing benchmark history `{path}`: {err}");
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        })?;
+        Ok(Self { entries })
+    }
+
+    /// Saves the history to the specified path.
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut contents = serde_json::to_string_pretty(&self.entries)?;
+        contents.push('\n');
+        std::fs::write(path, contents)
+    }
+
+    // Options requiring the history are rejected on validation without the `serde` feature, so these methods
+    // shouldn't be called.
+    #[cfg(not(feature = "serde"))]
+    pub fn load(_path: &str) -> io::Result<Self> {
+        Err(Self::unsupported())
+    }
+
+    #[cfg(not(feature = "serde"))]
+    #[allow(clippy::unused_self)] // signature is consistent with the `serde` version
+    pub fn save(&self, _path: &str) -> io::Result<()> {
+        Err(Self::unsupported())
+    }
+
+    #[cfg(not(feature = "serde"))]
+    fn unsupported() -> io::Error {
+        let message = "benchmark history requires the `serde` crate feature";
+        io::Error::new(io::ErrorKind::Unsupported, message)
+    }
+
+    /// Appends `stats` recorded by the latest benchmark run to the history, retaining at most `capacity`
+    /// latest entries.
+    pub fn push(&mut self, stats: CachegrindStats, capacity: usize) {
+        for entry in &mut self.entries {
+            entry.is_latest_run = false;
+        }
+        self.entries.push(Entry {
+            stats,
+            is_latest_run: true,
+        });
+        let excess = self.entries.len().saturating_sub(capacity);
+        self.entries.drain(..excess);
+    }
+
+    /// Removes the entry recorded by the latest benchmark run (if any) and the entries after it. This is used
+    /// to compare the results of the latest run against the preceding history.
+    pub fn truncate_latest_run(&mut self) {
+        if let Some(pos) = self.entries.iter().position(|entry| entry.is_latest_run) {
+            self.entries.truncate(pos);
+        }
+    }
+
+    /// Aggregates stats in the history. Only entries with the same kind of stats as the latest entry are considered.
+    pub fn aggregate(&self, comparison: Comparison) -> Option<CachegrindStats> {
+        let latest = &self.entries.last()?.stats;
+        let mut entries: Vec<_> = self
+            .entries
+            .iter()
+            .map(|entry| entry.stats)
+            .filter(|stats| mem::discriminant(stats) == mem::discriminant(latest))
+            .collect();
+
+        match comparison {
+            Comparison::Prev => entries.last().copied(),
+            Comparison::HistoryMedian => {
+                // Take the entire median entry rather than per-stat medians, so that the aggregated stats
+                // are consistent with each other (e.g., cache misses don't exceed the total number of accesses).
+                entries.sort_by_key(CachegrindStats::total_instructions);
+                Some(entries[(entries.len() - 1) / 2])
+            }
+            Comparison::HistoryMean => {
+                let fields: Vec<_> = entries.iter().map(stats_to_fields).collect();
+                let aggregated: Vec<_> = (0..fields[0].len())
+                    .map(|i| mean(fields.iter().map(|fields| fields[i])))
+                    .collect();
+                Some(stats_from_fields(latest, &aggregated))
+            }
+        }
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)] // the mean cannot exceed `u64::MAX`
+fn mean(values: impl ExactSizeIterator<Item = u64>) -> u64 {
+    let len = values.len() as u128;
+    let sum: u128 = values.map(u128::from).sum();
+    (sum / len) as u64
+}
+
+fn stats_to_fields(stats: &CachegrindStats) -> Vec<u64> {
+    match stats {
+        CachegrindStats::Simple { instructions } => vec![*instructions],
+        CachegrindStats::Heap { instructions, heap } => {
+            vec![*instructions, heap.allocations, heap.bytes]
+        }
+        CachegrindStats::Full(stats) => [stats.instructions, stats.data_reads, stats.data_writes]
+            .into_iter()
+            .flat_map(|point| [point.total, point.l1_misses, point.l3_misses])
+            .collect(),
+    }
+}
+
+/// Inverse of [`stats_to_fields()`] for stats of the same kind as `template`.
+fn stats_from_fields(template: &CachegrindStats, fields: &[u64]) -> CachegrindStats {
+    let point = |i: usize| CachegrindDataPoint {
+        total: fields[i],
+        l1_misses: fields[i + 1],
+        l3_misses: fields[i + 2],
+    };
+
+    match template {
+        CachegrindStats::Simple { .. } => CachegrindStats::simple(fields[0]),
+        CachegrindStats::Heap { .. } => CachegrindStats::with_heap(
+            fields[0],
+            HeapStats {
+                allocations: fields[1],
+                bytes: fields[2],
+            },
+        ),
+        CachegrindStats::Full(_) => CachegrindStats::Full(FullCachegrindStats {
+            instructions: point(0),
+            data_reads: point(3),
+            data_writes: point(6),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full_stats(instructions: u64) -> CachegrindStats {
+        let point = |total| CachegrindDataPoint {
+            total,
+            l1_misses: total / 10,
+            l3_misses: total / 100,
+        };
+        CachegrindStats::Full(FullCachegrindStats {
+            instructions: point(instructions),
+            data_reads: point(instructions / 2),
+            data_writes: point(instructions / 4),
+        })
+    }
+
+    fn stats(history: &History) -> Vec<CachegrindStats> {
+        history.entries.iter().map(|entry| entry.stats).collect()
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn saving_and_loading_history() {
+        let mut history = History::default();
+        history.push(CachegrindStats::simple(100), 3);
+        history.push(full_stats(1_000), 3);
+        let heap = HeapStats {
+            allocations: 2,
+            bytes: 64,
+        };
+        history.push(CachegrindStats::with_heap(500, heap), 3);
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.history.json");
+        let path = path.to_str().unwrap();
+        history.save(path).unwrap();
+        let loaded = History::load(path).unwrap();
+        assert_eq!(loaded.entries, history.entries);
+
+        let missing = History::load("/non-existing/history.json").unwrap();
+        assert!(missing.entries.is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializing_history_entries() {
+        let mut history = History::default();
+        history.push(CachegrindStats::simple(100), 3);
+        let heap = HeapStats {
+            allocations: 2,
+            bytes: 64,
+        };
+        history.push(CachegrindStats::with_heap(500, heap), 3);
+
+        let json = serde_json::to_value(&history.entries).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                { "stats": { "kind": "simple", "instructions": 100 } },
+                {
+                    "stats": {
+                        "kind": "heap",
+                        "instructions": 500,
+                        "heap": { "allocations": 2, "bytes": 64 },
+                    },
+                    "is_latest_run": true,
+                },
+            ])
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn parsing_invalid_history() {
+        let parse = serde_json::from_str::<Vec<Entry>>;
+        assert!(parse("").is_err());
+        assert!(parse(r#"[{ "stats": { "instructions": 1 } }]"#).is_err());
+        assert!(parse(r#"[{ "stats": { "kind": "simple", "instructions": -1 } }]"#).is_err());
+        assert!(parse("[]").unwrap().is_empty());
+    }
+
+    #[test]
+    fn capping_history() {
+        let mut history = History::default();
+        for instructions in 0..10 {
+            history.push(CachegrindStats::simple(instructions), 3);
+        }
+        let expected: Vec<_> = (7..10).map(CachegrindStats::simple).collect();
+        assert_eq!(stats(&history), expected);
+        assert!(history.entries.last().unwrap().is_latest_run);
+    }
+
+    #[test]
+    fn truncating_latest_run() {
+        let mut history = History::default();
+        for instructions in [100, 200] {
+            history.push(CachegrindStats::simple(instructions), 5);
+        }
+        history.truncate_latest_run();
+        assert_eq!(stats(&history), [CachegrindStats::simple(100)]);
+
+        // The latest entry isn't marked, e.g. because the history was truncated previously.
+        history.truncate_latest_run();
+        assert_eq!(stats(&history), [CachegrindStats::simple(100)]);
+    }
+
+    #[test]
+    fn aggregating_history() {
+        let mut history = History::default();
+        assert_eq!(history.aggregate(Comparison::HistoryMedian), None);
+
+        for instructions in [1_000, 3_000, 1_200, 1_100] {
+            history.push(full_stats(instructions), 5);
+        }
+        assert_eq!(
+            history.aggregate(Comparison::HistoryMedian),
+            Some(full_stats(1_100))
+        );
+        assert_eq!(
+            history.aggregate(Comparison::HistoryMean),
+            Some(full_stats(1_575))
+        );
+        assert_eq!(history.aggregate(Comparison::Prev), Some(full_stats(1_100)));
+
+        // Entries with another kind of stats should be ignored.
+        history.push(CachegrindStats::simple(500), 5);
+        assert_eq!(
+            history.aggregate(Comparison::HistoryMedian),
+            Some(CachegrindStats::simple(500))
+        );
+    }
+
+    #[test]
+    fn median_is_a_history_entry() {
+        let entry = |instructions, l1_misses| {
+            let point = CachegrindDataPoint {
+                total: instructions,
+                l1_misses,
+                l3_misses: 0,
+            };
+            CachegrindStats::Full(FullCachegrindStats {
+                instructions: point,
+                data_reads: point,
+                data_writes: point,
+            })
+        };
+
+        let mut history = History::default();
+        for (instructions, l1_misses) in [(1_000, 900), (1_100, 10), (900, 50)] {
+            history.push(entry(instructions, l1_misses), 5);
+        }
+        // Per-stat medians would yield (1_000, 50).
+        assert_eq!(
+            history.aggregate(Comparison::HistoryMedian),
+            Some(entry(1_000, 900))
+        );
+    }
+}
@@ -1,17 +1,31 @@
 //! Benchmark identifiers.
 
 use std::{
-    fmt,
+    cmp::Ordering,
+    collections::BTreeMap,
+    env, fmt,
+    fmt::Write as _,
+    fs,
     hash::{Hash, Hasher},
     panic::Location,
+    path::{Path, PathBuf},
+    sync::OnceLock,
 };
 
+#[cfg(feature = "serde")]
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
 /// Benchmark identifier supplied to [`Bencher`](crate::Bencher) functions.
+///
+/// An ID may have [tags](Self::tag()) attached, e.g. to group benchmarks in dashboards. Tags
+/// do not participate in ID comparisons or hashing. IDs are ordered by the name, then by args, so that
+/// parametric benchmarks with the same name are grouped together.
 #[derive(Debug, Clone)]
 pub struct BenchmarkId {
     pub(crate) name: String,
     pub(crate) location: &'static Location<'static>,
     pub(crate) args: Option<String>, // TODO: is this needed?
+    pub(crate) tags: BTreeMap<String, String>,
 }
 
 impl PartialEq for BenchmarkId {
@@ -35,6 +49,20 @@ impl PartialEq<&str> for BenchmarkId {
 
 impl Eq for BenchmarkId {}
 
+impl PartialOrd for BenchmarkId {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BenchmarkId {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.name
+            .cmp(&other.name)
+            .then_with(|| self.args.cmp(&other.args))
+    }
+}
+
 impl Hash for BenchmarkId {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.name.hash(state);
@@ -59,11 +87,38 @@ impl<S: Into<String>> From<S> for BenchmarkId {
             name: name.into(),
             location: Location::caller(),
             args: None,
+            tags: BTreeMap::new(),
         }
     }
 }
 
 impl BenchmarkId {
+    /// Returns a path (relative to the output directory, and without an extension) to store benchmark outputs at.
+    ///
+    /// Chars not safe for file names are percent-encoded. `/` separators are retained, so that the outputs
+    /// for parametric benchmarks are grouped in a directory, unless they would lead to empty path segments.
+    /// Dots are encoded at the start of path segments to avoid hidden files and `.` / `..` segments.
+    pub(crate) fn to_path(&self) -> String {
+        let id = self.to_string();
+        let bytes = id.as_bytes();
+        let mut path = String::with_capacity(id.len());
+        for (i, &byte) in bytes.iter().enumerate() {
+            let is_segment_start = i == 0 || bytes[i - 1] == b'/';
+            let is_safe = match byte {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' => true,
+                b'.' => !is_segment_start,
+                b'/' => !is_segment_start && bytes.get(i + 1).is_some_and(|&next| next != b'/'),
+                _ => false,
+            };
+            if is_safe {
+                path.push(char::from(byte));
+            } else {
+                write!(path, "%{byte:02X}").unwrap();
+            }
+        }
+        path
+    }
+
     /// Creates an ID consisting of the base function name, and an argument representation.
     #[track_caller]
     pub fn new(name: impl Into<String>, args: impl fmt::Display) -> Self {
@@ -71,6 +126,289 @@ impl BenchmarkId {
             name: name.into(),
             location: Location::caller(),
             args: Some(args.to_string()),
+            tags: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a tag with the specified `key` and `value` to this ID, replacing the existing value for the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use yab::{black_box, Bencher, BenchmarkId};
+    ///
+    /// fn benchmarks(bencher: &mut Bencher) {
+    ///     let id = BenchmarkId::new("parse", 1_000)
+    ///         .tag("category", "parsing")
+    ///         .tag("owner", "team-a");
+    ///     assert_eq!(id, BenchmarkId::new("parse", 1_000)); // tags are not compared
+    ///     bencher.bench(id, || black_box("1000").parse::<u64>());
+    /// }
+    /// # yab::main!(benchmarks);
+    /// ```
+    #[must_use]
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.insert(key.into(), value.into());
+        self
+    }
+
+    /// Returns tags attached to this ID.
+    pub fn tags(&self) -> &BTreeMap<String, String> {
+        &self.tags
+    }
+
+    /// Replaces the string presentation of this ID with `name`, retaining the location and tags.
+    pub(crate) fn renamed(self, name: String) -> Self {
+        Self {
+            name,
+            args: None,
+            ..self
+        }
+    }
+
+    /// Returns the file containing the benchmark definition. Absolute paths inside the workspace
+    /// are made relative to the workspace root, so that the location is stable across environments.
+    pub(crate) fn location_file(&self) -> &'static str {
+        normalize_file(self.location.file(), workspace_root())
+    }
+}
+
+/// Returns the workspace root determined from `CARGO_MANIFEST_DIR` at runtime. The variable is set by Cargo
+/// when running benchmarks / tests.
+pub(crate) fn workspace_root() -> Option<&'static Path> {
+    static ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+    let root = ROOT.get_or_init(|| {
+        let manifest_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR")?);
+        Some(find_workspace_root(&manifest_dir).to_owned())
+    });
+    root.as_deref()
+}
+
+/// Finds the topmost ancestor of `manifest_dir` with a manifest defining a workspace. If there is no such ancestor,
+/// returns `manifest_dir` itself.
+fn find_workspace_root(manifest_dir: &Path) -> &Path {
+    let is_workspace_root = |dir: &Path| {
+        fs::read_to_string(dir.join("Cargo.toml"))
+            .is_ok_and(|manifest| defines_workspace(&manifest))
+    };
+    manifest_dir
+        .ancestors()
+        .filter(|dir| is_workspace_root(dir))
+        .last()
+        .unwrap_or(manifest_dir)
+}
+
+/// Checks whether a manifest defines a workspace. This is a cheap line-based scan rather than full TOML parsing;
+/// it recognizes `[workspace]` / `[workspace.*]` table headers and top-level `workspace` / `workspace.*` keys.
+fn defines_workspace(manifest: &str) -> bool {
+    let is_workspace_key = |key: &str| key == "workspace" || key.starts_with("workspace.");
+    let mut is_top_level = true;
+    manifest.lines().map(str::trim).any(|line| {
+        if let Some(header) = line.strip_prefix('[') {
+            is_top_level = false;
+            header
+                .split_once(']')
+                .is_some_and(|(table, _)| is_workspace_key(table.trim()))
+        } else {
+            is_top_level
+                && line
+                    .split_once('=')
+                    .is_some_and(|(key, _)| is_workspace_key(key.trim()))
+        }
+    })
+}
+
+fn normalize_file(file: &'static str, root: Option<&Path>) -> &'static str {
+    let Some(root) = root else {
+        return file;
+    };
+    Path::new(file)
+        .strip_prefix(root)
+        .ok()
+        .and_then(Path::to_str)
+        .unwrap_or(file)
+}
+
+/// Serializes the ID as an object with the string presentation of the ID and tags (if any).
+#[cfg(feature = "serde")]
+impl Serialize for BenchmarkId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let field_count = if self.tags.is_empty() { 1 } else { 2 };
+        let mut state = serializer.serialize_struct("BenchmarkId", field_count)?;
+        state.serialize_field("id", &self.to_string())?;
+        if self.tags.is_empty() {
+            state.skip_field("tags")?;
+        } else {
+            state.serialize_field("tags", &self.tags)?;
+        }
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_path(path: &str) -> String {
+        let mut bytes = vec![];
+        let mut iter = path.bytes();
+        while let Some(byte) = iter.next() {
+            if byte == b'%' {
+                let hex = [iter.next().unwrap(), iter.next().unwrap()];
+                let hex = std::str::from_utf8(&hex).unwrap();
+                bytes.push(u8::from_str_radix(hex, 16).unwrap());
+            } else {
+                bytes.push(byte);
+            }
+        }
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn converting_id_to_path() {
+        let id = BenchmarkId::new("fib", 15);
+        assert_eq!(id.to_path(), "fib/15");
+        let id = BenchmarkId::from("fib_short");
+        assert_eq!(id.to_path(), "fib_short");
+        let id = BenchmarkId::new("parse", "a:b c");
+        assert_eq!(id.to_path(), "parse/a%3Ab%20c");
+        let id = BenchmarkId::new("path", "../etc/passwd");
+        assert_eq!(id.to_path(), "path/%2E./etc/passwd");
+        let id = BenchmarkId::new("", "/x/");
+        assert_eq!(id.to_path(), "%2F%2Fx%2F");
+        let id = BenchmarkId::new("unicode", "\u{444}%");
+        assert_eq!(id.to_path(), "unicode/%D1%84%25");
+    }
+
+    #[test]
+    fn normalizing_location_file() {
+        let root = Path::new("/home/user/project");
+        assert_eq!(
+            normalize_file("/home/user/project/benches/fib.rs", Some(root)),
+            "benches/fib.rs"
+        );
+        assert_eq!(
+            normalize_file("benches/fib.rs", Some(root)),
+            "benches/fib.rs"
+        );
+        assert_eq!(
+            normalize_file("/home/user/.cargo/registry/lib.rs", Some(root)),
+            "/home/user/.cargo/registry/lib.rs"
+        );
+        assert_eq!(
+            normalize_file("/home/user/project/benches/fib.rs", None),
+            "/home/user/project/benches/fib.rs"
+        );
+    }
+
+    #[test]
+    fn finding_workspace_root() {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let root = find_workspace_root(manifest_dir);
+        assert_ne!(root, manifest_dir);
+        assert!(manifest_dir.starts_with(root));
+        assert!(root.join("Cargo.toml").is_file());
+    }
+
+    #[test]
+    fn detecting_workspace_manifests() {
+        assert!(defines_workspace("[workspace]\nmembers = [\"crates/*\"]\n"));
+        assert!(defines_workspace(
+            "# Comment\n[ workspace.package ]\nversion = \"0.1.0\"\n"
+        ));
+        assert!(defines_workspace(
+            "workspace = { members = [\"lib\"] }\n\n[package]\nname = \"app\"\n"
+        ));
+        assert!(defines_workspace("workspace.members = [\"lib\"]\n"));
+
+        assert!(!defines_workspace(
+            "[package]\nname = \"lib\"\nversion.workspace = true\n"
+        ));
+        assert!(!defines_workspace(
+            "[package]\nname = \"lib\"\nworkspace = \"../..\"\n"
+        ));
+        assert!(!defines_workspace(
+            "[dependencies]\nserde.workspace = true\n"
+        ));
+        assert!(!defines_workspace("[[bin]]\nname = \"workspace\"\n"));
+    }
+
+    #[test]
+    fn finding_workspace_root_without_workspace_header() {
+        let root = tempfile::tempdir().unwrap();
+        let manifest =
+            "[workspace.package]\nversion = \"0.1.0\"\n\n[workspace.dependencies]\nserde = \"1\"\n";
+        fs::write(root.path().join("Cargo.toml"), manifest).unwrap();
+        let manifest_dir = root.path().join("crates/lib");
+        fs::create_dir_all(&manifest_dir).unwrap();
+        fs::write(
+            manifest_dir.join("Cargo.toml"),
+            "[package]\nname = \"lib\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(find_workspace_root(&manifest_dir), root.path());
+    }
+
+    #[test]
+    fn tags_do_not_affect_id_comparisons() {
+        let id = BenchmarkId::new("fib", 15);
+        let tagged_id = BenchmarkId::new("fib", 15).tag("category", "math");
+        assert_eq!(tagged_id, id);
+        assert_eq!(tagged_id, "fib/15");
+        assert_eq!(tagged_id.to_string(), "fib/15");
+        assert_eq!(tagged_id.tags().len(), 1);
+        assert_eq!(tagged_id.tags()["category"], "math");
+    }
+
+    #[test]
+    fn ordering_ids() {
+        let mut ids = [
+            BenchmarkId::new("fib", 20),
+            BenchmarkId::from("fib-short"),
+            BenchmarkId::from("fib"),
+            BenchmarkId::new("fib", 10).tag("category", "math"),
+        ];
+        ids.sort();
+        let ids: Vec<_> = ids.iter().map(ToString::to_string).collect();
+        assert_eq!(ids, ["fib", "fib/10", "fib/20", "fib-short"]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializing_id() {
+        let id = BenchmarkId::new("fib", 15);
+        let json = serde_json::to_value(&id).unwrap();
+        assert_eq!(json, serde_json::json!({ "id": "fib/15" }));
+
+        let id = id.tag("category", "math");
+        let json = serde_json::to_value(&id).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "id": "fib/15", "tags": { "category": "math" } })
+        );
+    }
+
+    #[test]
+    fn id_paths_roundtrip() {
+        let ids = [
+            BenchmarkId::new("fib", 15),
+            BenchmarkId::new("parse", "a:b c"),
+            BenchmarkId::new("path", "../etc/passwd"),
+            BenchmarkId::new("", "/x/"),
+            BenchmarkId::new("a/", "/b"),
+            BenchmarkId::new(".hidden", "..."),
+            BenchmarkId::new("unicode", "\u{444}%2F"),
+        ];
+        for id in ids {
+            let path = id.to_path();
+            assert!(
+                path.split('/')
+                    .all(|segment| !segment.is_empty() && !segment.starts_with('.')),
+                "{path}"
+            );
+            assert_eq!(decode_path(&path), id.to_string());
         }
     }
 }
@@ -6,9 +6,12 @@
 //!
 //! - Supports newer `cachegrind` versions and customizing the `cachegrind` wrapper.
 //! - Supports capturing only instruction counts (i.e., not simulating CPU caches).
+//! - Supports collecting heap allocation stats using [DHAT] instead of `cachegrind` (`--tool=dhat`;
+//!   requires the [`serde` feature](#serde)).
 //! - Conditionally injects `CACHEGRIND_{START|STOP}_INSTRUMENTATION` macros (available in `cachegrind`
 //!   3.22.0+) allowing for more precise measurements. See [crate features](#crate-features) below.
-//! - Supports configurable warm-up (defined in terms of executed instructions) before the capture.
+//! - Supports configurable warm-up (defined in terms of executed instructions) before the capture,
+//!   which can be overridden for individual benchmarks.
 //!
 //! # How to use
 //!
@@ -70,6 +73,9 @@
 //! Derives `serde::{Serialize, Deserialize}` for [`BenchmarkOutput`], [`CachegrindStats`] and related
 //! types. Useful to save benchmark outputs when using a custom [`BenchmarkProcessor`].
 //!
+//! Also required for the features relying on JSON (de)serialization: maintaining the benchmark history
+//! (`--history` and `--vs` command-line options) and collecting stats with DHAT (`--tool=dhat`).
+//!
 //! ## `instrumentation`
 //!
 //! *(Off by default)*
@@ -78,6 +84,13 @@
 //! Requires `cachegrind` 3.22.0+ with dev headers available; see [`crabgrind` docs](https://crates.io/crates/crabgrind)
 //! for details.
 //!
+//! ## `http`
+//!
+//! *(Off by default)*
+//!
+//! Allows posting benchmark results as JSON to an HTTP endpoint, e.g. a dashboard collecting results
+//! from CI runs, via the `--report-url` and `--report-token` command-line options. Enables the `serde` feature.
+//!
 //! # Examples
 //!
 //! The entrypoint for defining benchmarks is [`Bencher`].
@@ -120,6 +133,7 @@
 //! ```
 //!
 //! [`cachegrind`]: https://valgrind.org/docs/manual/cg-manual.html
+//! [DHAT]: https://valgrind.org/docs/manual/dh-manual.html
 
 // Documentation settings.
 #![doc(html_root_url = "https://docs.rs/yab/0.1.0")]
@@ -131,24 +145,56 @@
 pub use std::hint::black_box;
 
 pub use crate::{
-    bencher::{BenchMode, Bencher},
+    bencher::{BenchConfig, BenchError, BenchMode, Bencher, GlobalSetup},
     cachegrind::{
-        AccessSummary, CachegrindDataPoint, CachegrindStats, Capture, CaptureGuard,
-        FullCachegrindStats,
+        AccessSummary, CachegrindDataPoint, CachegrindError, CachegrindStats, Capture,
+        CaptureGuard, ExecFailure, FullCachegrindStats, HeapStats, IncompatibleStatsError,
     },
     id::BenchmarkId,
 };
 
 mod bencher;
 mod cachegrind;
+mod history;
 mod id;
 mod options;
 pub mod reporter;
 mod utils;
 
+/// Passes the value through [`black_box`] and then drops it.
+///
+/// Use this function for values computed in the captured region (e.g., while a [`CaptureGuard`] is alive)
+/// that are not used afterward. Unlike [`black_box`], which is an identity function, `black_box_drop()`
+/// consumes the value, so it cannot be accidentally left unused (and optimized away together with
+/// the computations producing it). The value is dropped during the call, so if the call is
+/// in the captured region, the destructor is captured as well.
+///
+/// # Examples
+///
+/// ```
+/// use yab::{black_box, black_box_drop, Bencher};
+///
+/// fn benchmarks(bencher: &mut Bencher) {
+///     bencher.bench_with_capture("collect", |capture| {
+///         let input = vec![1_u64; 1_000];
+///         let _guard = capture.start();
+///         // Without `black_box_drop()`, the compiler may eliminate computing the sum.
+///         black_box_drop(black_box(&input).iter().sum::<u64>());
+///     });
+/// }
+/// # yab::main!(benchmarks);
+/// ```
+#[inline]
+pub fn black_box_drop<T>(value: T) {
+    drop(black_box(value));
+}
+
 /// Wraps a provided function to create the entrypoint for a benchmark executable. The function
 /// must have `fn(&mut` [`Bencher`]`)` signature.
 ///
+/// On failure, the executable exits with a code distinguishing the failure kind; see [`BenchError::exit_code()`]
+/// for details.
+///
 /// # Examples
 ///
 /// See [crate docs](index.html) for the examples of usage.
@@ -156,7 +202,12 @@ mod utils;
 macro_rules! main {
     ($function:path) => {
         fn main() {
-            $function(&mut $crate::Bencher::default());
+            let mut bencher = $crate::Bencher::default();
+            $function(&mut bencher);
+            if let Err(err) = bencher.try_run() {
+                // The error is already reported
+                ::std::process::exit(err.exit_code());
+            }
         }
     };
 }
@@ -1,10 +1,25 @@
-use std::{env, io, io::IsTerminal, num, num::NonZeroUsize, process, process::Command};
+use std::{
+    env,
+    ffi::OsString,
+    io,
+    io::IsTerminal,
+    num,
+    num::NonZeroUsize,
+    path,
+    path::{Path, PathBuf},
+    process,
+    process::Command,
+    thread,
+};
 
-use clap::{ColorChoice, Parser};
+use clap::{Args, ColorChoice, Parser, ValueEnum};
 use regex::Regex;
 
 use crate::{
     bencher::BenchMode,
+    cachegrind::{CachegrindVersion, Tool},
+    history::Comparison,
+    id::workspace_root,
     reporter::{PrintingReporter, Verbosity},
     BenchmarkId,
 };
@@ -17,11 +32,126 @@ const DEFAULT_CACHEGRIND_WRAPPER: &[&str] = &[
     "--cache-sim=yes",
     #[cfg(feature = "instrumentation")]
     "--instr-at-start=no",
-    "--I1=32768,8,64",
-    "--D1=32768,8,64",
-    "--LL=8388608,16,64",
 ];
 
+/// Default seed provided to benchmarks via [`Bencher::seed()`](crate::Bencher::seed()).
+const DEFAULT_SEED: u64 = 123;
+
+const DEFAULT_DHAT_WRAPPER: &[&str] = &["setarch", "-R", "valgrind", "--tool=dhat"];
+
+/// `cachegrind` flags that are only supported starting from a certain version.
+const VERSION_GATED_FLAGS: &[(&str, CachegrindVersion)] = &[
+    ("--cache-sim", CachegrindVersion(3, 4, 0)),
+    ("--branch-sim", CachegrindVersion(3, 4, 0)),
+    ("--instr-at-start", CachegrindVersion::INSTRUMENTATION),
+];
+
+/// Cache geometry simulated by `cachegrind`, translated to `--I1`, `--D1` and `--LL` args.
+/// `cachegrind` records the geometry in its raw outputs.
+#[derive(Debug, Clone, Args)]
+pub(crate) struct CacheOptions {
+    /// Size of L1 instruction and data caches in bytes.
+    #[arg(long, default_value_t = 32_768)]
+    l1_size: u64,
+    /// Associativity of L1 instruction and data caches.
+    #[arg(long, default_value_t = 8)]
+    l1_assoc: u64,
+    /// Line size of L1 instruction and data caches in bytes. Must be a power of 2.
+    #[arg(long, default_value_t = 64)]
+    l1_line: u64,
+    /// Size of the last-level cache in bytes.
+    #[arg(long, default_value_t = 8_388_608)]
+    ll_size: u64,
+    /// Associativity of the last-level cache.
+    #[arg(long, default_value_t = 16)]
+    ll_assoc: u64,
+    /// Line size of the last-level cache in bytes. Must be a power of 2.
+    #[arg(long, default_value_t = 64)]
+    ll_line: u64,
+    /// Comma-separated sizes of the last-level cache to run each benchmark with, e.g. `4M,8M,16M`
+    /// (`K` / `M` / `G` suffixes denote binary units). Each benchmark is run once per size, with the size appended
+    /// to the benchmark ID (e.g., `fib/20 @ LL=8M`); hence, the total runtime is multiplied by the number of sizes.
+    /// Overrides `--ll-size`.
+    #[arg(long, value_name = "SIZES", value_delimiter = ',', value_parser = parse_cache_size)]
+    sweep_ll_size: Vec<u64>,
+}
+
+impl CacheOptions {
+    fn validate(&self) -> Result<(), String> {
+        Self::validate_cache("L1", self.l1_size, self.l1_assoc, self.l1_line, true)?;
+        // `cachegrind` supports LL caches with a non-power-of-2 number of sets
+        Self::validate_cache("LL", self.ll_size, self.ll_assoc, self.ll_line, false)?;
+        for &size in &self.sweep_ll_size {
+            Self::validate_cache("LL", size, self.ll_assoc, self.ll_line, false)?;
+        }
+        Ok(())
+    }
+
+    fn validate_cache(
+        name: &str,
+        size: u64,
+        assoc: u64,
+        line: u64,
+        requires_pow2_sets: bool,
+    ) -> Result<(), String> {
+        if !line.is_power_of_two() {
+            return Err(format!("{name} line size must be a power of 2, got {line}"));
+        }
+        // `assoc * line` may overflow for user-provided values.
+        let set_size = assoc.checked_mul(line);
+        let Some(set_size) =
+            set_size.filter(|&set_size| set_size > 0 && size > 0 && size % set_size == 0)
+        else {
+            return Err(format!(
+                "{name} size ({size}) must be a positive multiple of associativity ({assoc}) * line size ({line})"
+            ));
+        };
+        let sets = size / set_size;
+        if requires_pow2_sets && !sets.is_power_of_two() {
+            return Err(format!(
+                "{name} number of sets (size / (associativity * line size) = {sets}) must be a power of 2"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns `cachegrind` flags together with their values specifying the cache geometry.
+    fn args(&self) -> [(&'static str, String); 3] {
+        let l1 = format!("{},{},{}", self.l1_size, self.l1_assoc, self.l1_line);
+        let ll = format!("{},{},{}", self.ll_size, self.ll_assoc, self.ll_line);
+        [("--I1", l1.clone()), ("--D1", l1), ("--LL", ll)]
+    }
+}
+
+/// Order of printing benchmark outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum SortOrder {
+    /// Print outputs as soon as benchmarks complete.
+    None,
+    /// Print outputs once all benchmarks complete, sorted by benchmark name.
+    Name,
+}
+
+/// Format of the benchmark list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum ListFormat {
+    /// Print `{name}: benchmark` lines, similar to the standard test harness.
+    Terse,
+    /// Print a JSON array of objects with benchmark names, locations and tags.
+    Json,
+}
+
+/// Policy for keeping raw cachegrind outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum KeepOutput {
+    /// Always keep outputs.
+    Always,
+    /// Remove outputs both for successful and failed benchmarks.
+    Never,
+    /// Keep outputs only for failed benchmarks.
+    OnFailure,
+}
+
 #[allow(clippy::struct_excessive_bools)] // fine for command-line args
 #[derive(Debug, Clone, Parser)]
 pub(crate) struct BenchOptions {
@@ -30,6 +160,8 @@ pub(crate) struct BenchOptions {
     bench: bool,
 
     /// Wrapper to call `cachegrind` as. Beware that changing params will likely render results not comparable.
+    /// If any arg contains `{OUT}`, it will be replaced with the output path; otherwise, the path will be specified
+    /// via an additional `--cachegrind-out-file` arg.
     #[arg(
         long,
         alias = "cg",
@@ -38,24 +170,146 @@ pub(crate) struct BenchOptions {
         default_values_t = DEFAULT_CACHEGRIND_WRAPPER.iter().copied().map(str::to_owned)
     )]
     cachegrind_wrapper: Vec<String>,
+    #[command(flatten)]
+    cache: CacheOptions,
+    /// Environment variable (`KEY=VALUE`) to set for the benchmarked process spawned under the wrapper.
+    /// Can be specified multiple times.
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    env: Vec<(String, String)>,
+    /// Working directory for the benchmarked process spawned under the wrapper. By default, the current directory
+    /// is used. Paths to `cachegrind` outputs and a relative wrapper program (e.g., `--cg=./valgrind.sh`)
+    /// are still resolved relative to the current directory.
+    #[arg(long, value_name = "PATH")]
+    working_dir: Option<PathBuf>,
+    /// Pin the processes spawned under the wrapper to the specified CPU core by prepending `taskset -c CPU`
+    /// to the wrapper. This may improve reproducibility on machines with heterogeneous cores (e.g., big.LITTLE).
+    /// Only supported on Linux; requires `taskset` (e.g., from `util-linux`) to be on PATH. With `--jobs` > 1,
+    /// all benchmarks are pinned to the same core.
+    #[arg(long, value_name = "CPU")]
+    cpu_affinity: Option<usize>,
+    /// Fail benchmarks if Valgrind reports errors when running them, by appending `--error-exitcode=1`
+    /// to the wrapper (unless the wrapper already specifies an error exit code). Valgrind stderr is included
+    /// into the benchmark error. Beware that neither `cachegrind` nor DHAT check memory accesses (this is done
+    /// by `memcheck`), so this only catches errors detected by the Valgrind core.
+    #[arg(long)]
+    strict_valgrind: bool,
+    /// Show stdout / stderr of the processes spawned under cachegrind (e.g., debug prints in benchmarks) instead of
+    /// capturing them. The output will interleave with the benchmark reports, and will include the output
+    /// of the cachegrind wrapper itself. Each benchmark is spawned several times (for calibration, baseline and
+    /// full runs), so the output will be repeated.
+    #[arg(long)]
+    pub show_output: bool,
+    /// Valgrind tool used to collect stats. DHAT collects heap allocation stats in addition to instruction counts,
+    /// but doesn't simulate CPU caches. DHAT stats cover the entire benchmark process (less the baseline run)
+    /// rather than the captured region. DHAT requires the `serde` crate feature.
+    #[arg(long, value_enum, default_value_t = Tool::Cachegrind)]
+    pub tool: Tool,
+    /// Wrapper to call DHAT as. Only used with `--tool=dhat`. Similar to the `cachegrind` wrapper,
+    /// `{OUT}` in args will be replaced with the output path.
+    #[arg(
+        long,
+        env = "DHAT_WRAPPER",
+        value_delimiter = ':',
+        default_values_t = DEFAULT_DHAT_WRAPPER.iter().copied().map(str::to_owned)
+    )]
+    dhat_wrapper: Vec<String>,
     /// Target number of instructions for the benchmark warm-up. Note that this number may not be reached
-    /// for very fast benchmarks.
+    /// for very fast benchmarks. 0 means no warm-up, i.e. a single measured iteration.
     #[arg(long = "warm-up", default_value_t = 1_000_000)]
     pub warm_up_instructions: u64,
+    /// Number of additional warm-up iterations performed before the captured iteration, on top of ones
+    /// performed to reach the `--warm-up` target. Unlike `--warm-up`, these iterations aren't limited
+    /// by `--max-iterations`. Iterations are run in the same process as the captured one, both for baseline
+    /// and full runs. Since `cachegrind` simulation is deterministic, warm-up mainly affects the simulated cache
+    /// and the allocator state.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub warmup_runs: u64,
     /// Maximum number of iterations for a single benchmark.
     #[arg(long, default_value_t = 1_000)]
     pub max_iterations: u64,
-    /// Base directory to put cachegrind outputs into. Will be created if absent.
-    #[arg(long, default_value = "target/yab", env = "CACHEGRIND_OUT_DIR")]
-    pub cachegrind_out_dir: String,
-    /// Maximum number of benchmarks to run in parallel.
+    /// Base directory to put cachegrind outputs into. Will be created if absent. Relative paths are resolved
+    /// against the current directory. [default: `yab` in the Cargo target directory, i.e. `CARGO_TARGET_DIR`
+    /// or `target` in the workspace root; `build.target-dir` in Cargo config files is not honored]
+    #[arg(
+        long = "cachegrind-out-dir",
+        value_name = "DIR",
+        env = "CACHEGRIND_OUT_DIR"
+    )]
+    cachegrind_out_dir: Option<String>,
+    /// When to keep raw cachegrind outputs after processing them. Beware that removed outputs
+    /// cannot be compared against during the following runs or printed with `--print`.
+    #[arg(long, value_enum, default_value_t = KeepOutput::Always)]
+    pub keep_cachegrind_output: KeepOutput,
+    /// Do not warn if the cachegrind (or DHAT) wrapper doesn't appear to disable address space layout randomization
+    /// (ASLR).
+    #[arg(long, env = "CACHEGRIND_ALLOW_ASLR")]
+    allow_aslr: bool,
+    /// Do not back up previous cachegrind outputs as `*.old` files. Without backups, `--print` will not
+    /// compare results against the previous run.
+    #[arg(long)]
+    pub no_backup: bool,
+    /// Compare benchmark results against the stored outputs (or the stats specified via `--vs` / `--vs-dir`)
+    /// without writing to the output directory, e.g. in a read-only checkout. Raw cachegrind outputs are written
+    /// to a temporary directory and are removed after processing; the benchmark history isn't updated.
+    #[arg(long, conflicts_with = "print")]
+    pub compare_only: bool,
+    /// Temporary directory for raw outputs with `--compare-only`. Set by the bencher, which owns the directory.
+    #[arg(skip)]
+    pub compare_only_dir: Option<String>,
+    /// Number of latest results to keep in the benchmark history (`<id>.history.json` in the output dir).
+    /// 0 means that the history is not maintained. Requires the `serde` crate feature.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub history: usize,
+    /// What to compare benchmark results against: the previous run, or the median / mean of the benchmark history
+    /// (requires `--history` for the history to be maintained). Comparing against the history requires
+    /// the `serde` crate feature.
+    #[arg(long, value_enum, default_value_t = Comparison::Prev)]
+    pub vs: Comparison,
+    /// Compare benchmark results against `cachegrind` outputs in the specified directory rather than the previous
+    /// run. The directory must have the same layout as the output dir, e.g. be a copy of the output dir
+    /// from another run.
+    #[arg(long, value_name = "DIR", conflicts_with = "vs")]
+    pub vs_dir: Option<String>,
+    /// Seed provided to benchmarks, e.g. to generate random inputs. The seed is recorded in raw cachegrind outputs
+    /// as a part of the command line of the instrumented executable.
+    #[arg(long, env = "YAB_SEED", default_value_t = DEFAULT_SEED)]
+    pub seed: u64,
+    /// Maximum number of benchmarks to run in parallel. `auto` uses half of the available parallelism,
+    /// leaving headroom for other processes (which also makes reported wall-clock times more stable)
+    /// at the cost of longer runs.
     #[arg(
         long,
         short = 'j',
         env = "CACHEGRIND_JOBS",
+        value_name = "N|auto",
+        value_parser = parse_jobs,
         default_value_t = NonZeroUsize::new(num_cpus::get().max(1)).unwrap()
     )]
     pub jobs: NonZeroUsize,
+    /// Fail a benchmark if it has captured zero instructions (after subtracting the baseline). This usually means
+    /// that the benchmarked code is empty or the capture isn't started around it.
+    #[arg(long)]
+    pub fail_on_zero: bool,
+    /// Collect approximate stats in a single cachegrind run per benchmark, skipping calibration and the baseline run.
+    /// The stats include the benchmark setup and the fixed overhead of the executable, so they are not comparable
+    /// to stats collected in the normal mode. Hence, they are neither saved nor compared to previous stats.
+    #[arg(long, conflicts_with_all = ["vs", "vs_dir"])]
+    pub fast: bool,
+    /// Warn if the captured region accounts for less than the specified percentage of instructions
+    /// executed per benchmark iteration, which may mean that the benchmark is dominated by setup or that the capture
+    /// is misplaced. 0 disables the warning.
+    #[arg(long, value_name = "PERCENT", default_value_t = 10, value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub min_captured_share: u8,
+    /// Stop starting new benchmarks after the first fatal benchmark error. Benchmarks that are already running
+    /// will still complete. By default, all benchmarks are run, and the process exits with an error
+    /// once they are completed.
+    #[arg(long)]
+    pub fail_fast: bool,
+    /// Collect all matching benchmarks before running any of them. By default, benchmarks are run as soon
+    /// as they are defined; collecting them first allows reporters to learn the total number of benchmarks
+    /// beforehand (e.g., to display progress).
+    #[arg(long)]
+    pub collect_first: bool,
 
     /// Sets coloring of the program output.
     #[arg(long, env = "COLOR", default_value_t = ColorChoice::Auto)]
@@ -66,36 +320,299 @@ pub(crate) struct BenchOptions {
     /// Output only basic benchmarking information.
     #[arg(long, short = 'q', conflicts_with = "verbose")]
     pub quiet: bool,
+    /// Order of printing benchmark outputs. Sorting outputs means that they are printed only after all benchmarks
+    /// are completed.
+    #[arg(long, value_enum, default_value_t = SortOrder::None)]
+    pub sort: SortOrder,
+    /// Write test outcomes as a `JUnit` XML document to the specified path. Only used in the test mode.
+    #[arg(long, value_name = "PATH", env = "YAB_JUNIT")]
+    junit: Option<String>,
+    /// Write benchmark results as a self-contained HTML report with a sortable table to the specified path.
+    /// Not used in the test mode.
+    // Not exposed as `--format=html --output PATH`: `--format` selects how `--list` is printed to stdout,
+    // while the HTML report is written *in addition* to terminal output, similar to `--junit`.
+    #[arg(long, value_name = "PATH", env = "YAB_HTML")]
+    html: Option<String>,
+    /// Write all file reports applicable to the mode into the specified directory: `junit.xml` in the test mode
+    /// (see `--junit`) and `report.html` otherwise (see `--html`). Explicitly specified report paths take precedence.
+    #[arg(long, value_name = "DIR", env = "YAB_REPORT_DIR")]
+    report_dir: Option<String>,
+    /// Post benchmark results as a JSON array to the specified HTTP(S) endpoint once all benchmarks
+    /// are completed. Failing to post results is reported as a warning. Not used in the test mode.
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "URL", env = "YAB_REPORT_URL")]
+    pub report_url: Option<String>,
+    /// Bearer token to authorize posting results to `--report-url`.
+    #[cfg(feature = "http")]
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        env = "YAB_REPORT_TOKEN",
+        requires = "report_url",
+        hide_env_values = true
+    )]
+    pub report_token: Option<String>,
 
+    /// Print `cachegrind` commands that would be spawned for each benchmark (calibration, baseline and full runs)
+    /// instead of running benchmarks. The iteration counts for the baseline and full runs are upper bounds since
+    /// they are determined by calibration. Useful to debug the `cachegrind` wrapper configuration.
+    #[arg(long, requires = "bench", conflicts_with_all = ["list", "print"])]
+    pub dry_run: bool,
     /// List all benchmarks instead of running them.
     #[arg(long, conflicts_with = "print")]
     list: bool,
+    /// Format of the benchmark list. Only used with `--list`.
+    #[arg(long, value_enum, default_value_t = ListFormat::Terse)]
+    pub format: ListFormat,
+    /// Annotate cache access rows in the benchmark output with their contribution to the estimated CPU cycles.
+    #[arg(long)]
+    pub show_contributions: bool,
+    /// Only print outputs for benchmarks that have changed compared to the previous run beyond the noise threshold
+    /// (see `--noise-threshold`), or have no previous data. Unchanged benchmarks are only counted in the summary.
+    #[arg(long)]
+    pub changed_only: bool,
+    /// Relative change (in percent) up to which a difference with the previous stats is considered noise.
+    /// Such differences are printed dimmed rather than colored. A benchmark is considered regressed (improved)
+    /// in the summary and with `--changed-only` if any of its summary stats (instructions, heap stats, cache hits,
+    /// RAM accesses, or estimated cycles) regressed (improved) beyond the threshold.
+    #[arg(long, value_name = "PERCENT", default_value_t = 1.0, value_parser = parse_percentage)]
+    pub noise_threshold: f64,
     /// Prints latest benchmark results without running benchmarks.
     #[arg(long, conflicts_with = "list")]
     print: bool,
     /// Match benchmark names exactly.
     #[arg(long)]
     exact: bool,
+    /// Do not fail if FILTER or `--filter-tag` match no benchmarks. By default, unmatched filters (e.g., misspelled
+    /// ones) are treated as invalid usage. Beware that each benchmark executable is checked separately; with several
+    /// bench targets, either specify the target (`cargo bench --bench NAME`), or run benchmarks via `cargo yab`,
+    /// which only fails if filters match no benchmarks in all targets.
+    #[arg(long)]
+    pub allow_no_matches: bool,
+    /// File to record whether FILTER and `--filter-tag` have matched any benchmarks to, instead of failing
+    /// if they haven't. Set by `cargo yab` to check matches across all bench targets.
+    #[arg(long, value_name = "PATH", env = "YAB_MATCHES_FILE", hide = true)]
+    pub matches_file: Option<String>,
+    /// Skip benchmarks that do not have the specified tag (`key=value`). If specified multiple times,
+    /// benchmarks must have all specified tags. Combined with FILTER, benchmarks must match both.
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    filter_tag: Vec<(String, String)>,
+    /// Skip benchmarks not defined in the specified file, e.g. to only run benchmarks in files changed
+    /// according to `git diff --name-only`. If specified multiple times, benchmarks must be defined in any
+    /// of the files. A path matches the benchmark location if one of them ends with the other
+    /// (e.g., `benches/all.rs` matches `crates/app/benches/all.rs`). Combined with other filters, benchmarks must match
+    /// all of them. Unlike FILTER and `--filter-tag`, this filter doesn't fail the run if it matches no benchmarks,
+    /// since most benchmark executables are expected to have no matching benchmarks.
+    #[arg(long, value_name = "PATH")]
+    filter_file: Vec<PathBuf>,
     /// Skip benchmarks whose names do not match FILTER (a regular expression).
     #[arg(name = "FILTER")]
     filter: Option<String>,
 }
 
+fn parse_key_value(s: &str) -> Result<(String, String), &'static str> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or("value must have `key=value` form")?;
+    Ok((key.to_owned(), value.to_owned()))
+}
+
+/// Parses a cache size in bytes, with an optional `K` / `M` / `G` binary unit suffix.
+fn parse_cache_size(s: &str) -> Result<u64, String> {
+    let (number, multiplier) = match s.as_bytes().last() {
+        Some(b'K') => (&s[..s.len() - 1], 1 << 10),
+        Some(b'M') => (&s[..s.len() - 1], 1 << 20),
+        Some(b'G') => (&s[..s.len() - 1], 1 << 30),
+        _ => (s, 1),
+    };
+    let number: u64 = number.parse().map_err(|err| format!("{err}"))?;
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| "cache size is too large".to_owned())
+}
+
+/// Formats a cache size using the largest binary unit that divides it.
+fn format_cache_size(size: u64) -> String {
+    for (unit, multiplier) in [("G", 1 << 30), ("M", 1 << 20), ("K", 1 << 10)] {
+        if size > 0 && size % multiplier == 0 {
+            return format!("{}{unit}", size / multiplier);
+        }
+    }
+    size.to_string()
+}
+
+/// Returns the default output directory: `yab` in the Cargo target directory. Consistently with Cargo,
+/// a relative `CARGO_TARGET_DIR` is resolved against the current directory, and the default `target` dir
+/// is placed in the workspace root.
+///
+/// Cargo config files are not read, so `build.target-dir` specified in them is not honored.
+fn default_out_dir(
+    target_dir: Option<OsString>,
+    workspace_root: Option<&Path>,
+    current_dir: Option<&Path>,
+) -> String {
+    let target_dir = match (target_dir, workspace_root) {
+        (Some(target_dir), _) => {
+            let target_dir = PathBuf::from(target_dir);
+            match current_dir {
+                Some(dir) => dir.join(target_dir),
+                None => target_dir,
+            }
+        }
+        (None, Some(root)) => root.join("target"),
+        (None, None) => PathBuf::from("target"),
+    };
+    target_dir.join("yab").to_string_lossy().into_owned()
+}
+
+/// Resolves a relative `path` against the current directory.
+pub(crate) fn absolute_path(path: &str) -> String {
+    if Path::new(path).is_absolute() {
+        return path.to_owned();
+    }
+    match env::current_dir() {
+        Ok(current_dir) => current_dir.join(path).to_string_lossy().into_owned(),
+        Err(_) => path.to_owned(),
+    }
+}
+
+fn parse_percentage(s: &str) -> Result<f64, String> {
+    let value: f64 = s.parse().map_err(|err| format!("{err}"))?;
+    if value.is_finite() && value >= 0.0 {
+        Ok(value)
+    } else {
+        Err("percentage must be a non-negative finite number".to_owned())
+    }
+}
+
+fn parse_jobs(s: &str) -> Result<NonZeroUsize, num::ParseIntError> {
+    if s == "auto" {
+        Ok(auto_jobs(
+            thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        ))
+    } else {
+        s.parse()
+    }
+}
+
+/// Caps the number of jobs at half of the available parallelism. Each `cachegrind` process fully loads a CPU core,
+/// so running a job per logical CPU may oversubscribe physical cores (e.g., with hyper-threading).
+fn auto_jobs(available_parallelism: usize) -> NonZeroUsize {
+    NonZeroUsize::new(available_parallelism / 2).unwrap_or(NonZeroUsize::MIN)
+}
+
 impl BenchOptions {
+    /// Placeholder for the output path in wrapper args.
+    const OUT_PLACEHOLDER: &'static str = "{OUT}";
+
     pub fn validate(&self, reporter: &mut PrintingReporter) -> bool {
         reporter.report_debug(format_args!("Started benchmarking with options: {self:?}"));
 
-        if self.warm_up_instructions == 0 {
-            reporter.report_error(None, &"`warm_up_instructions` must be positive");
-            return false;
-        }
         if self.max_iterations == 0 {
             reporter.report_error(None, &"`max_iterations` must be positive");
             return false;
         }
+        if let Err(err) = self.cache.validate() {
+            reporter.report_error(None, &err);
+            return false;
+        }
+        if let Err(err) = self.check_features() {
+            reporter.report_error(None, &err);
+            return false;
+        }
         true
     }
 
+    /// Checks that options relying on optional crate features are only used if these features are enabled.
+    fn check_features(&self) -> Result<(), &'static str> {
+        if cfg!(feature = "serde") {
+            return Ok(());
+        }
+        if self.history > 0 || self.vs != Comparison::Prev {
+            return Err(
+                "benchmark history (`--history` and `--vs`) requires the `serde` crate feature",
+            );
+        }
+        if self.tool == Tool::Dhat {
+            return Err("`--tool=dhat` requires the `serde` crate feature");
+        }
+        Ok(())
+    }
+
+    /// Checks whether the `cachegrind` version is recent enough for the enabled features
+    /// and the wrapper flags. Discrepancies are reported as warnings since the wrapper may be customized.
+    pub fn check_cachegrind_version(
+        &self,
+        version: CachegrindVersion,
+        reporter: &mut PrintingReporter,
+    ) {
+        for warning in self.cachegrind_version_warnings(version) {
+            reporter.report_warning(None, &warning);
+        }
+    }
+
+    fn cachegrind_version_warnings(&self, version: CachegrindVersion) -> Vec<String> {
+        if self.tool != Tool::Cachegrind {
+            return vec![];
+        }
+
+        let mut warnings = vec![];
+        if cfg!(feature = "instrumentation") && version < CachegrindVersion::INSTRUMENTATION {
+            warnings.push(format!(
+                "`instrumentation` feature requires cachegrind {required}+, but the installed version \
+                 is {version}; benchmarks will likely fail",
+                required = CachegrindVersion::INSTRUMENTATION
+            ));
+        }
+
+        for &(flag, required) in VERSION_GATED_FLAGS {
+            let is_used = self
+                .cachegrind_wrapper
+                .iter()
+                .any(|arg| arg == flag || arg.starts_with(&format!("{flag}=")));
+            if is_used && version < required {
+                warnings.push(format!(
+                    "cachegrind flag `{flag}` requires cachegrind {required}+, but the installed version \
+                     is {version}"
+                ));
+            }
+        }
+        warnings
+    }
+
+    /// Returns the wrapper for the used tool.
+    fn wrapper(&self) -> &[String] {
+        match self.tool {
+            Tool::Cachegrind => &self.cachegrind_wrapper,
+            Tool::Dhat => &self.dhat_wrapper,
+        }
+    }
+
+    /// Heuristically checks whether the wrapper for the used tool disables ASLR via `setarch -R`.
+    fn disables_aslr(&self) -> bool {
+        let mut args = self.wrapper().iter();
+        let has_setarch = args
+            .by_ref()
+            .any(|arg| Path::new(arg).file_name() == Some("setarch".as_ref()));
+        has_setarch && args.any(|arg| arg == "-R" || arg == "--addr-no-randomize")
+    }
+
+    /// Warns if the wrapper for the used tool doesn't appear to disable ASLR, unless this check is disabled
+    /// via `--allow-aslr`.
+    pub fn check_aslr(&self, reporter: &mut PrintingReporter) {
+        if !self.allow_aslr && !self.disables_aslr() {
+            let tool = match self.tool {
+                Tool::Cachegrind => "cachegrind",
+                Tool::Dhat => "DHAT",
+            };
+            let warning = format!(
+                "{tool} wrapper doesn't appear to disable ASLR (e.g., via `setarch -R`); \
+                 benchmark results may be non-reproducible across runs. Use `--allow-aslr` to suppress this warning"
+            );
+            reporter.report_warning(None, &warning);
+        }
+    }
+
     pub fn mode(&self) -> BenchMode {
         if self.list {
             BenchMode::List
@@ -126,6 +643,73 @@ impl BenchOptions {
         }
     }
 
+    /// Returns the path to write the `JUnit` report to, if any.
+    pub fn junit_path(&self) -> Option<String> {
+        self.report_path(self.junit.as_ref(), "junit.xml")
+    }
+
+    /// Returns the path to write the HTML report to, if any.
+    pub fn html_path(&self) -> Option<String> {
+        self.report_path(self.html.as_ref(), "report.html")
+    }
+
+    fn report_path(&self, explicit_path: Option<&String>, file_name: &str) -> Option<String> {
+        if let Some(path) = explicit_path {
+            return Some(path.clone());
+        }
+        let report_dir = Path::new(self.report_dir.as_ref()?);
+        Some(report_dir.join(file_name).to_string_lossy().into_owned())
+    }
+
+    /// Returns the base directory for cachegrind outputs. The default directory doesn't depend on the current directory
+    /// if the workspace root can be determined (i.e., if the benchmark is run by Cargo), unless a relative
+    /// `CARGO_TARGET_DIR` is specified.
+    pub fn cachegrind_out_dir(&self) -> String {
+        if let Some(dir) = &self.cachegrind_out_dir {
+            return dir.clone();
+        }
+        let current_dir = env::current_dir().ok();
+        default_out_dir(
+            env::var_os("CARGO_TARGET_DIR"),
+            workspace_root(),
+            current_dir.as_deref(),
+        )
+    }
+
+    /// Resolves a relative `path` against the current directory if the working directory for spawned processes
+    /// is overridden. Paths without separators (e.g., executable names looked up in `PATH`) are not changed.
+    pub fn resolve_path(&self, path: &str) -> String {
+        if self.working_dir.is_none() || !path.contains(path::MAIN_SEPARATOR) {
+            return path.to_owned();
+        }
+        absolute_path(path)
+    }
+
+    /// Returns options for each swept cache geometry together with the geometry label to append to benchmark IDs.
+    /// If the geometry isn't swept, returns a single copy of these options without a label.
+    pub fn geometry_variants(&self) -> Vec<(Option<String>, Self)> {
+        if self.tool != Tool::Cachegrind || self.cache.sweep_ll_size.is_empty() {
+            return vec![(None, self.clone())];
+        }
+        let sizes = self.cache.sweep_ll_size.iter();
+        sizes
+            .map(|&size| {
+                let mut options = self.clone();
+                options.cache.ll_size = size;
+                options.cache.sweep_ll_size.clear();
+                (Some(format!("LL={}", format_cache_size(size))), options)
+            })
+            .collect()
+    }
+
+    pub fn tag_matcher(&self) -> TagMatcher {
+        TagMatcher(self.filter_tag.clone())
+    }
+
+    pub fn file_matcher(&self) -> FileMatcher {
+        FileMatcher(self.filter_file.clone())
+    }
+
     pub fn id_matcher(&self) -> Result<IdMatcher, regex::Error> {
         Ok(match &self.filter {
             None => IdMatcher::Any,
@@ -134,22 +718,69 @@ impl BenchOptions {
         })
     }
 
+    /// Creates a command for the wrapper. If any wrapper arg contains the [`Self::OUT_PLACEHOLDER`],
+    /// it is substituted with the output path; otherwise, the output path is specified via an additional arg.
+    /// For `cachegrind`, cache geometry flags are added unless they are already present in the wrapper.
+    /// Environment variables specified via `--env` are set for the command. If `--working-dir` is specified,
+    /// the output path and a relative wrapper program containing a path separator are resolved
+    /// against the current directory.
     pub fn cachegrind_wrapper(&self, out_file: &str) -> Command {
-        let mut command = Command::new(&self.cachegrind_wrapper[0]);
-        command.args(&self.cachegrind_wrapper[1..]);
-        command.arg(format!("--cachegrind-out-file={out_file}"));
+        let out_file = &self.resolve_path(out_file);
+        let wrapper = self.wrapper();
+        let has_placeholder = wrapper
+            .iter()
+            .any(|arg| arg.contains(Self::OUT_PLACEHOLDER));
+        let mut args = wrapper
+            .iter()
+            .map(|arg| arg.replace(Self::OUT_PLACEHOLDER, out_file));
+
+        // A relative program path (e.g., `./scripts/cachegrind.sh`) would be resolved against the working dir
+        // for the spawned process, so it's made absolute.
+        let program = self.resolve_path(&args.next().expect("empty wrapper"));
+        let mut command = if let Some(cpu) = self.cpu_affinity {
+            let mut command = Command::new("taskset");
+            command.args(["-c".to_owned(), cpu.to_string(), program]);
+            command
+        } else {
+            Command::new(program)
+        };
+        command.args(args);
+        command.envs(self.env.iter().map(|(key, value)| (key, value)));
+        if let Some(working_dir) = &self.working_dir {
+            command.current_dir(working_dir);
+        }
+        if self.tool == Tool::Cachegrind {
+            for (flag, value) in self.cache.args() {
+                let prefix = format!("{flag}=");
+                if !wrapper.iter().any(|arg| arg.starts_with(&prefix)) {
+                    command.arg(format!("{prefix}{value}"));
+                }
+            }
+        }
+        if self.strict_valgrind
+            && !wrapper
+                .iter()
+                .any(|arg| arg.starts_with("--error-exitcode="))
+        {
+            command.arg("--error-exitcode=1");
+        }
+        if !has_placeholder {
+            command.arg(format!("{}={out_file}", self.tool.out_file_arg()));
+        }
         command
     }
 }
 
 #[derive(Debug, thiserror::Error)]
 enum CachegrindOptionsError {
-    #[error("too few args; should be used as `--cachegrind-instrument ITERS +|- ID")]
+    #[error("too few args; should be used as `--cachegrind-instrument ITERS +|- ID SEED")]
     TooFewArgs,
     #[error("failed parsing iterations (must be a positive integer): {0}")]
     Iterations(#[source] num::ParseIntError),
     #[error("failed parsing baseline flag")]
     IsBaseline,
+    #[error("failed parsing seed (must be a non-negative integer): {0}")]
+    Seed(#[source] num::ParseIntError),
 }
 
 #[derive(Debug)]
@@ -158,15 +789,12 @@ pub(crate) struct CachegrindOptions {
     pub is_baseline: bool,
     pub id: String,
     // TODO: consider index?
+    pub seed: u64,
 }
 
 impl CachegrindOptions {
     const MARKER: &'static str = "--cachegrind-instrument";
 
-    fn new() -> Result<Option<Self>, CachegrindOptionsError> {
-        Self::parse_args(env::args())
-    }
-
     pub fn push_args(&self, command: &mut Command) {
         let is_baseline = if self.is_baseline { "+" } else { "-" };
         command.args([
@@ -174,6 +802,7 @@ impl CachegrindOptions {
             &self.iterations.to_string(),
             is_baseline,
             &self.id,
+            &self.seed.to_string(),
         ]);
     }
 
@@ -196,10 +825,13 @@ impl CachegrindOptions {
             _ => return Err(CachegrindOptionsError::IsBaseline),
         };
         let id = args.next().ok_or(CachegrindOptionsError::TooFewArgs)?;
+        let seed = args.next().ok_or(CachegrindOptionsError::TooFewArgs)?;
+        let seed = seed.parse().map_err(CachegrindOptionsError::Seed)?;
         Ok(Some(Self {
             iterations,
             is_baseline,
             id,
+            seed,
         }))
     }
 }
@@ -221,15 +853,50 @@ impl IdMatcher {
     }
 }
 
+/// Matches benchmark tags. An ID matches if it has all tags in the matcher.
+#[derive(Debug)]
+pub(crate) struct TagMatcher(Vec<(String, String)>);
+
+impl TagMatcher {
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.0
+    }
+
+    pub fn matches(&self, id: &BenchmarkId) -> bool {
+        self.0
+            .iter()
+            .all(|(key, value)| id.tags().get(key) == Some(value))
+    }
+}
+
+/// Matches files containing benchmark definitions. An ID matches if it's defined in any of the files
+/// in the matcher, or if the matcher is empty.
+#[derive(Debug)]
+pub(crate) struct FileMatcher(Vec<PathBuf>);
+
+impl FileMatcher {
+    pub fn matches(&self, id: &BenchmarkId) -> bool {
+        if self.0.is_empty() {
+            return true;
+        }
+        let location = Path::new(id.location_file());
+        self.0
+            .iter()
+            .any(|path| location.ends_with(path) || path.ends_with(location))
+    }
+}
+
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)] // options are created once per process
 pub(crate) enum Options {
     Bench(BenchOptions),
     Cachegrind(CachegrindOptions),
 }
 
 impl Options {
-    pub fn new() -> Self {
-        match CachegrindOptions::new() {
+    /// Parses options from the provided command-line args. The first arg is the executable name.
+    pub fn from_args(args: Vec<String>) -> Self {
+        match CachegrindOptions::parse_args(args.iter().cloned()) {
             Err(err) => {
                 eprintln!("Failed starting instrumented binary: {err}");
                 process::exit(1);
@@ -238,7 +905,7 @@ impl Options {
             Ok(None) => { /* continue */ }
         }
 
-        let options = BenchOptions::parse();
+        let options = BenchOptions::parse_from(args);
         Self::Bench(options)
     }
 }
@@ -259,7 +926,7 @@ mod tests {
         let options = CachegrindOptions::parse_args(args);
         assert_matches!(options, Ok(None));
 
-        let args = ["yab", "--cachegrind-instrument", "123", "+", "fib"]
+        let args = ["yab", "--cachegrind-instrument", "123", "+", "fib", "42"]
             .map(str::to_owned)
             .into_iter();
         let options = CachegrindOptions::parse_args(args)
@@ -268,5 +935,471 @@ mod tests {
         assert_eq!(options.iterations, 123);
         assert!(options.is_baseline);
         assert_eq!(options.id, "fib");
+        assert_eq!(options.seed, 42);
+
+        let args = ["yab", "--cachegrind-instrument", "123", "+", "fib"]
+            .map(str::to_owned)
+            .into_iter();
+        let err = CachegrindOptions::parse_args(args).unwrap_err();
+        assert_matches!(err, CachegrindOptionsError::TooFewArgs);
+    }
+
+    #[test]
+    fn parsing_job_count() {
+        let options = BenchOptions::parse_from(["yab", "-j", "3"]);
+        assert_eq!(options.jobs.get(), 3);
+        let options = BenchOptions::parse_from(["yab", "--jobs=auto"]);
+        assert!(options.jobs.get() <= num_cpus::get().max(1));
+        BenchOptions::try_parse_from(["yab", "--jobs=0"]).unwrap_err();
+
+        assert_eq!(auto_jobs(1).get(), 1);
+        assert_eq!(auto_jobs(2).get(), 1);
+        assert_eq!(auto_jobs(16).get(), 8);
+    }
+
+    #[test]
+    fn matching_tags() {
+        let options = BenchOptions::parse_from(["yab", "--filter-tag", "category=parsing"]);
+        let matcher = options.tag_matcher();
+        assert!(matcher.matches(&BenchmarkId::from("fib").tag("category", "parsing")));
+        assert!(!matcher.matches(&BenchmarkId::from("fib").tag("category", "math")));
+        assert!(!matcher.matches(&BenchmarkId::from("fib")));
+
+        let options = BenchOptions::parse_from([
+            "yab",
+            "--filter-tag=category=parsing",
+            "--filter-tag=owner=a=b",
+        ]);
+        let matcher = options.tag_matcher();
+        let id = BenchmarkId::from("fib").tag("category", "parsing");
+        assert!(!matcher.matches(&id));
+        assert!(matcher.matches(&id.tag("owner", "a=b")));
+
+        let err = BenchOptions::try_parse_from(["yab", "--filter-tag=category"]).unwrap_err();
+        assert!(err.to_string().contains("key=value"), "{err}");
+    }
+
+    #[test]
+    fn matching_files() {
+        let id = BenchmarkId::from("fib");
+        let location = id.location_file();
+        assert!(location.ends_with("src/options.rs"), "{location}");
+
+        let options = BenchOptions::parse_from(["yab"]);
+        assert!(options.file_matcher().matches(&id));
+
+        let options = BenchOptions::parse_from(["yab", "--filter-file=src/options.rs"]);
+        assert!(options.file_matcher().matches(&id));
+        let absolute_path = env::current_dir().unwrap().join("src/options.rs");
+        let options = BenchOptions::parse_from([
+            "yab".as_ref(),
+            "--filter-file".as_ref(),
+            absolute_path.as_os_str(),
+        ]);
+        assert!(options.file_matcher().matches(&id));
+
+        let options = BenchOptions::parse_from([
+            "yab",
+            "--filter-file=src/bencher.rs",
+            "--filter-file=ions.rs",
+        ]);
+        assert!(!options.file_matcher().matches(&id));
+    }
+
+    fn command_args(command: &Command) -> Vec<&str> {
+        let program = command.get_program().to_str().unwrap();
+        let args = command.get_args().map(|arg| arg.to_str().unwrap());
+        iter::once(program).chain(args).collect()
+    }
+
+    #[test]
+    fn creating_cachegrind_wrapper() {
+        let options = BenchOptions::parse_from(["yab", "--cg=valgrind", "--cg=--tool=cachegrind"]);
+        let command = options.cachegrind_wrapper("out/fib.cachegrind");
+        assert_eq!(
+            command_args(&command),
+            [
+                "valgrind",
+                "--tool=cachegrind",
+                "--I1=32768,8,64",
+                "--D1=32768,8,64",
+                "--LL=8388608,16,64",
+                "--cachegrind-out-file=out/fib.cachegrind"
+            ]
+        );
+
+        let options = BenchOptions::parse_from([
+            "yab",
+            "--cg=remote-run",
+            "--cg=--out={OUT}",
+            "--cg=valgrind",
+            "--cg=--tool=cachegrind",
+        ]);
+        let command = options.cachegrind_wrapper("out/fib.cachegrind");
+        assert_eq!(
+            command_args(&command),
+            [
+                "remote-run",
+                "--out=out/fib.cachegrind",
+                "valgrind",
+                "--tool=cachegrind",
+                "--I1=32768,8,64",
+                "--D1=32768,8,64",
+                "--LL=8388608,16,64",
+            ]
+        );
+    }
+
+    #[test]
+    fn setting_env_for_wrapper() {
+        let options = BenchOptions::parse_from([
+            "yab",
+            "--env",
+            "CONFIG_PATH=/etc/bench.toml",
+            "--env=EMPTY=",
+        ]);
+        let command = options.cachegrind_wrapper("out/fib.cachegrind");
+        let envs: Vec<_> = command
+            .get_envs()
+            .map(|(key, value)| (key.to_str().unwrap(), value.unwrap().to_str().unwrap()))
+            .collect();
+        assert_eq!(envs, [("CONFIG_PATH", "/etc/bench.toml"), ("EMPTY", "")]);
+
+        BenchOptions::try_parse_from(["yab", "--env", "NO_VALUE"]).unwrap_err();
+    }
+
+    #[test]
+    fn sweeping_cache_geometry() {
+        let options =
+            BenchOptions::parse_from(["yab", "--cg=valgrind", "--sweep-ll-size=4M,12M,512K"]);
+        assert_eq!(options.cache.sweep_ll_size, [4 << 20, 12 << 20, 512 << 10]);
+        options.cache.validate().unwrap();
+
+        let variants = options.geometry_variants();
+        let labels: Vec<_> = variants.iter().map(|(label, _)| label.as_deref()).collect();
+        assert_eq!(labels, [Some("LL=4M"), Some("LL=12M"), Some("LL=512K")]);
+        let command = variants[1].1.cachegrind_wrapper("out/fib.cachegrind");
+        assert!(
+            command_args(&command).contains(&"--LL=12582912,16,64"),
+            "{command:?}"
+        );
+
+        let options = BenchOptions::parse_from(["yab", "--sweep-ll-size=4M,1000"]);
+        let err = options.cache.validate().unwrap_err();
+        assert!(err.contains("LL size (1000)"), "{err}");
+
+        let options = BenchOptions::parse_from(["yab"]);
+        let variants = options.geometry_variants();
+        assert_eq!(variants.len(), 1);
+        assert_eq!(variants[0].0, None);
+    }
+
+    #[test]
+    fn default_out_dir_resolution() {
+        let root = Path::new("/workspace");
+        let current_dir = Path::new("/workspace/crates/app");
+        assert_eq!(default_out_dir(None, None, None), "target/yab");
+        assert_eq!(
+            default_out_dir(None, Some(root), Some(current_dir)),
+            "/workspace/target/yab"
+        );
+        let target_dir = OsString::from("/tmp/target");
+        assert_eq!(
+            default_out_dir(Some(target_dir), Some(root), Some(current_dir)),
+            "/tmp/target/yab"
+        );
+        let target_dir = OsString::from("out");
+        assert_eq!(
+            default_out_dir(Some(target_dir.clone()), Some(root), Some(current_dir)),
+            "/workspace/crates/app/out/yab"
+        );
+        assert_eq!(
+            default_out_dir(Some(target_dir), Some(root), None),
+            "out/yab"
+        );
+
+        let options = BenchOptions::parse_from(["yab", "--cachegrind-out-dir", "custom"]);
+        assert_eq!(options.cachegrind_out_dir(), "custom");
+    }
+
+    #[test]
+    fn resolving_report_paths() {
+        let options = BenchOptions::parse_from(["yab"]);
+        assert_eq!(options.junit_path(), None);
+        assert_eq!(options.html_path(), None);
+
+        let options = BenchOptions::parse_from(["yab", "--report-dir", "reports"]);
+        assert_eq!(options.junit_path().unwrap(), "reports/junit.xml");
+        assert_eq!(options.html_path().unwrap(), "reports/report.html");
+
+        let args = ["yab", "--report-dir", "reports", "--html", "custom.html"];
+        let options = BenchOptions::parse_from(args);
+        assert_eq!(options.junit_path().unwrap(), "reports/junit.xml");
+        assert_eq!(options.html_path().unwrap(), "custom.html");
+    }
+
+    #[test]
+    fn parsing_cache_sizes() {
+        assert_eq!(parse_cache_size("1024").unwrap(), 1_024);
+        assert_eq!(parse_cache_size("32K").unwrap(), 32_768);
+        assert_eq!(parse_cache_size("8M").unwrap(), 8_388_608);
+        assert_eq!(parse_cache_size("1G").unwrap(), 1 << 30);
+        assert!(parse_cache_size("8MB").is_err());
+        assert!(parse_cache_size("M").is_err());
+
+        assert_eq!(format_cache_size(8_388_608), "8M");
+        assert_eq!(format_cache_size(12 << 20), "12M");
+        assert_eq!(format_cache_size(1_000), "1000");
+    }
+
+    #[test]
+    fn checking_aslr_in_wrapper() {
+        let options = BenchOptions::parse_from(["yab"]);
+        assert!(options.disables_aslr());
+        let options = BenchOptions::parse_from([
+            "yab",
+            "--cg=/usr/bin/setarch",
+            "--cg=--addr-no-randomize",
+            "--cg=valgrind",
+        ]);
+        assert!(options.disables_aslr());
+
+        let options = BenchOptions::parse_from(["yab", "--cg=valgrind", "--cg=--tool=cachegrind"]);
+        assert!(!options.disables_aslr());
+        let options = BenchOptions::parse_from(["yab", "--cg=valgrind", "--cg=-R"]);
+        assert!(!options.disables_aslr());
+
+        // The wrapper for the used tool is checked.
+        let options = BenchOptions::parse_from(["yab", "--tool=dhat"]);
+        assert!(options.disables_aslr());
+        let options = BenchOptions::parse_from(["yab", "--tool=dhat", "--dhat-wrapper=valgrind"]);
+        assert!(!options.disables_aslr());
+        let options = BenchOptions::parse_from(["yab", "--tool=dhat", "--cg=valgrind"]);
+        assert!(options.disables_aslr());
+    }
+
+    #[test]
+    fn setting_working_dir_for_wrapper() {
+        let options = BenchOptions::parse_from(["yab", "--cg=valgrind", "--working-dir=fixtures"]);
+        let command = options.cachegrind_wrapper("out/fib.cachegrind");
+        assert_eq!(command.get_current_dir(), Some(Path::new("fixtures")));
+        let current_dir = env::current_dir().unwrap();
+        let expected_out_arg = format!(
+            "--cachegrind-out-file={}",
+            current_dir.join("out/fib.cachegrind").display()
+        );
+        assert_eq!(*command_args(&command).last().unwrap(), expected_out_arg);
+
+        assert_eq!(options.resolve_path("yab-bench"), "yab-bench");
+        assert_eq!(options.resolve_path("/usr/bin/yab"), "/usr/bin/yab");
+
+        let args = ["yab", "--cg=scripts/valgrind.sh", "--working-dir=fixtures"];
+        let options = BenchOptions::parse_from(args);
+        let command = options.cachegrind_wrapper("out/fib.cachegrind");
+        assert_eq!(
+            Path::new(command.get_program()),
+            current_dir.join("scripts/valgrind.sh")
+        );
+        let args = [
+            "yab",
+            "--cg=scripts/valgrind.sh",
+            "--cpu-affinity=0",
+            "--working-dir=fixtures",
+        ];
+        let options = BenchOptions::parse_from(args);
+        let command = options.cachegrind_wrapper("out/fib.cachegrind");
+        let expected_program = current_dir.join("scripts/valgrind.sh");
+        assert_eq!(
+            command_args(&command)[3],
+            expected_program.to_str().unwrap()
+        );
+
+        let options = BenchOptions::parse_from(["yab"]);
+        assert_eq!(
+            options.resolve_path("out/fib.cachegrind"),
+            "out/fib.cachegrind"
+        );
+    }
+
+    #[test]
+    fn pinning_wrapper_to_cpu() {
+        let options = BenchOptions::parse_from(["yab", "--cg=valgrind", "--cpu-affinity=3"]);
+        let command = options.cachegrind_wrapper("out/fib.cachegrind");
+        let args = command_args(&command);
+        assert_eq!(args[..4], ["taskset", "-c", "3", "valgrind"]);
+
+        let options = BenchOptions::parse_from(["yab", "--cg=valgrind"]);
+        let command = options.cachegrind_wrapper("out/fib.cachegrind");
+        assert_eq!(command.get_program(), "valgrind");
+    }
+
+    #[test]
+    fn failing_on_valgrind_errors() {
+        let options = BenchOptions::parse_from(["yab", "--cg=valgrind", "--strict-valgrind"]);
+        let command = options.cachegrind_wrapper("out/fib.cachegrind");
+        assert_eq!(
+            command_args(&command),
+            [
+                "valgrind",
+                "--I1=32768,8,64",
+                "--D1=32768,8,64",
+                "--LL=8388608,16,64",
+                "--error-exitcode=1",
+                "--cachegrind-out-file=out/fib.cachegrind"
+            ]
+        );
+
+        let options = BenchOptions::parse_from([
+            "yab",
+            "--cg=valgrind",
+            "--cg=--error-exitcode=42",
+            "--strict-valgrind",
+        ]);
+        let command = options.cachegrind_wrapper("out/fib.cachegrind");
+        let args = command_args(&command);
+        assert!(!args.contains(&"--error-exitcode=1"), "{args:?}");
+    }
+
+    #[test]
+    fn specifying_cache_geometry() {
+        let options = BenchOptions::parse_from([
+            "yab",
+            "--cg=valgrind",
+            "--cg=--D1=16384,4,64",
+            "--l1-size=65536",
+            "--ll-size=12582912",
+            "--ll-assoc=12",
+            "--ll-line=128",
+        ]);
+        options.cache.validate().unwrap();
+        let command = options.cachegrind_wrapper("out/fib.cachegrind");
+        assert_eq!(
+            command_args(&command),
+            [
+                "valgrind",
+                "--D1=16384,4,64",
+                "--I1=65536,8,64",
+                "--LL=12582912,12,128",
+                "--cachegrind-out-file=out/fib.cachegrind"
+            ]
+        );
+
+        let options = BenchOptions::parse_from(["yab", "--tool=dhat"]);
+        let command = options.cachegrind_wrapper("out/fib.dhat");
+        assert!(!command_args(&command)
+            .iter()
+            .any(|arg| arg.starts_with("--I1=")));
+    }
+
+    #[test]
+    fn checking_cachegrind_version_for_instrumentation() {
+        let old_version = CachegrindVersion(3, 19, 0);
+        let options = BenchOptions::parse_from(["yab"]);
+        let warnings = options.cachegrind_version_warnings(old_version);
+        if cfg!(feature = "instrumentation") {
+            // The default wrapper also contains the `--instr-at-start` flag.
+            assert!(
+                warnings[0].starts_with("`instrumentation` feature requires cachegrind 3.22.0+"),
+                "{warnings:?}"
+            );
+        } else {
+            assert!(warnings.is_empty(), "{warnings:?}");
+        }
+
+        let options = BenchOptions::parse_from(["yab", "--tool=dhat"]);
+        assert!(options.cachegrind_version_warnings(old_version).is_empty());
+        let options = BenchOptions::parse_from(["yab"]);
+        assert!(options
+            .cachegrind_version_warnings(CachegrindVersion(3, 23, 0))
+            .is_empty());
+    }
+
+    fn assert_flag_warning(flag: &str, required: &str) {
+        let options =
+            BenchOptions::parse_from(["yab", "--cg=valgrind", &format!("--cg={flag}=yes")]);
+        let warnings = options.cachegrind_version_warnings(CachegrindVersion(3, 3, 0));
+        let expected = format!("cachegrind flag `{flag}` requires cachegrind {required}+");
+        assert!(
+            warnings
+                .iter()
+                .any(|warning| warning.starts_with(&expected)),
+            "{warnings:?}"
+        );
+
+        let warnings = options.cachegrind_version_warnings(CachegrindVersion(3, 23, 0));
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[test]
+    fn checking_cachegrind_version_for_cache_sim() {
+        assert_flag_warning("--cache-sim", "3.4.0");
+    }
+
+    #[test]
+    fn checking_cachegrind_version_for_branch_sim() {
+        assert_flag_warning("--branch-sim", "3.4.0");
+    }
+
+    #[test]
+    fn checking_cachegrind_version_for_instr_at_start() {
+        assert_flag_warning("--instr-at-start", "3.22.0");
+    }
+
+    #[test]
+    fn checking_features() {
+        let args = [
+            ["yab", "--history=5"],
+            ["yab", "--vs=history:median"],
+            ["yab", "--tool=dhat"],
+        ];
+        for args in args {
+            let options = BenchOptions::parse_from(args);
+            let result = options.check_features();
+            if cfg!(feature = "serde") {
+                result.unwrap();
+            } else {
+                let err = result.unwrap_err();
+                assert!(err.contains("requires the `serde` crate feature"), "{err}");
+            }
+        }
+        BenchOptions::parse_from(["yab"]).check_features().unwrap();
+    }
+
+    #[test]
+    fn validating_cache_geometry() {
+        let invalid_args = [
+            ("--l1-line=48", "L1 line size must be a power of 2"),
+            (
+                "--l1-assoc=0",
+                "L1 size (32768) must be a positive multiple",
+            ),
+            (
+                "--l1-assoc=6",
+                "L1 size (32768) must be a positive multiple",
+            ),
+            ("--l1-size=49152", "L1 number of sets"),
+            (
+                "--ll-size=1000",
+                "LL size (1000) must be a positive multiple",
+            ),
+        ];
+        for (arg, expected_err) in invalid_args {
+            let options = BenchOptions::parse_from(["yab", arg]);
+            let err = options.cache.validate().unwrap_err();
+            assert!(err.starts_with(expected_err), "{err}");
+        }
+
+        // Overflowing associativity * line size
+        let options =
+            BenchOptions::parse_from(["yab", "--l1-assoc=4294967296", "--l1-line=4294967296"]);
+        let err = options.cache.validate().unwrap_err();
+        assert!(
+            err.starts_with("L1 size (32768) must be a positive multiple"),
+            "{err}"
+        );
+
+        // Non-power-of-2 number of sets is fine for LL.
+        let options = BenchOptions::parse_from(["yab", "--ll-size=12582912", "--ll-assoc=12"]);
+        options.cache.validate().unwrap();
     }
 }
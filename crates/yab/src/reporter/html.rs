@@ -0,0 +1,401 @@
+//! Reporter implementation writing benchmark results as a self-contained HTML document.
+
+use std::{
+    cmp::Ordering,
+    io,
+    io::Write as _,
+    mem,
+    sync::{Arc, Mutex},
+};
+
+use super::{BenchmarkOutput, BenchmarkReporter, CyclesEstimator, MarkupEscaped, Reporter};
+use crate::{AccessSummary, BenchmarkId, CachegrindDataPoint, CachegrindStats};
+
+const STYLES: &str = "\
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+th, td { padding: 4px 12px; border-bottom: 1px solid #ddd; text-align: right; vertical-align: top; }
+th:first-child, td:first-child { text-align: left; }
+th { cursor: pointer; user-select: none; background: #f3f3f3; }
+.diff { display: block; font-size: 80%; }
+.regressed { color: #c00; }
+.improved { color: #080; }
+.noise { color: #888; }
+details table { margin-top: 4px; font-size: 85%; }";
+
+/// Sorts the table by the clicked column using `data-value` attributes of cells.
+const SCRIPT: &str = "\
+document.querySelectorAll('#benchmarks > thead th').forEach((th, column) => th.addEventListener('click', () => {
+  const body = document.getElementById('benchmarks').tBodies[0];
+  const ascending = th.dataset.order !== 'asc';
+  th.dataset.order = ascending ? 'asc' : 'desc';
+  const value = (row) => row.cells[column].dataset.value;
+  const rows = Array.from(body.rows).sort((a, b) => {
+    const [x, y] = [value(a), value(b)];
+    const cmp = column === 0 ? x.localeCompare(y) : (Number(x || -1) - Number(y || -1));
+    return ascending ? cmp : -cmp;
+  });
+  rows.forEach((row) => body.appendChild(row));
+}));";
+
+const COLUMNS: &[&str] = &[
+    "Benchmark",
+    "Instructions",
+    "L1 hits",
+    "L2/L3 hits",
+    "RAM accesses",
+    "Est. cycles",
+    "Allocations",
+    "Allocated bytes",
+];
+
+#[derive(Debug)]
+struct Entry {
+    id: BenchmarkId,
+    output: BenchmarkOutput,
+}
+
+type SharedEntries = Arc<Mutex<Vec<Entry>>>;
+
+/// Buffers benchmark outputs so that they can be written as an HTML document with a sortable table
+/// once all benchmarks are completed. Unlike other reporters, the document is written by the bencher
+/// via [`Self::write_to_file()`] rather than in [`Reporter::ok()`], so that I/O errors are reported
+/// as benchmarking errors.
+#[derive(Debug, Clone)]
+pub(crate) struct HtmlReporter {
+    out_path: String,
+    entries: SharedEntries,
+    cycles_estimator: CyclesEstimator,
+    noise_threshold: f64,
+}
+
+impl HtmlReporter {
+    pub fn new(out_path: String, cycles_estimator: CyclesEstimator, noise_threshold: f64) -> Self {
+        Self {
+            out_path,
+            entries: SharedEntries::default(),
+            cycles_estimator,
+            noise_threshold,
+        }
+    }
+
+    pub fn out_path(&self) -> &str {
+        &self.out_path
+    }
+
+    pub fn write_to_file(&self) -> io::Result<()> {
+        let file = super::create_report_file(&self.out_path)?;
+        let mut writer = io::BufWriter::new(file);
+        self.write_report(&mut writer)?;
+        writer.flush()
+    }
+
+    fn write_report(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        let mut entries = mem::take(&mut *self.entries.lock().expect("entries are poisoned"));
+        entries.sort_unstable_by(|entry, other| entry.id.cmp(&other.id));
+
+        writeln!(writer, "<!DOCTYPE html>")?;
+        writeln!(writer, r#"<html lang="en">"#)?;
+        writeln!(
+            writer,
+            r#"<head><meta charset="utf-8"><title>yab benchmarks</title>"#
+        )?;
+        writeln!(writer, "<style>\n{STYLES}\n</style>")?;
+        writeln!(writer, "</head>\n<body>")?;
+        writeln!(writer, r#"<table id="benchmarks">"#)?;
+        writeln!(writer, "<thead><tr>")?;
+        for column in COLUMNS {
+            writeln!(writer, "  <th>{column}</th>")?;
+        }
+        writeln!(writer, "</tr></thead>\n<tbody>")?;
+        for entry in &entries {
+            self.write_row(writer, entry)?;
+        }
+        writeln!(writer, "</tbody>\n</table>")?;
+        writeln!(writer, "<script>\n{SCRIPT}\n</script>")?;
+        writeln!(writer, "</body>\n</html>")
+    }
+
+    fn write_row(&self, writer: &mut impl io::Write, entry: &Entry) -> io::Result<()> {
+        let BenchmarkOutput {
+            stats, prev_stats, ..
+        } = &entry.output;
+        let prev_stats = prev_stats.as_ref();
+        let name = entry.id.to_string();
+
+        writeln!(writer, "<tr>")?;
+        write!(writer, r#"  <td data-value="{}">"#, MarkupEscaped(&name))?;
+        if let CachegrindStats::Full(full) = stats {
+            write!(
+                writer,
+                "<details><summary>{}</summary>",
+                MarkupEscaped(&name)
+            )?;
+            write_breakdown(writer, full.instructions, full.data_reads, full.data_writes)?;
+            writeln!(writer, "</details></td>")?;
+        } else {
+            writeln!(writer, "{}</td>", MarkupEscaped(&name))?;
+        }
+
+        let summary = stats.as_full().map(|&full| AccessSummary::from(full));
+        let prev_summary = prev_stats
+            .and_then(CachegrindStats::as_full)
+            .map(|&full| AccessSummary::from(full));
+        let cells = [
+            (
+                Some(stats.total_instructions()),
+                prev_stats.map(CachegrindStats::total_instructions),
+            ),
+            (
+                summary.map(|summary| summary.l1_hits),
+                prev_summary.map(|summary| summary.l1_hits),
+            ),
+            (
+                summary.map(|summary| summary.l3_hits),
+                prev_summary.map(|summary| summary.l3_hits),
+            ),
+            (
+                summary.map(|summary| summary.ram_accesses),
+                prev_summary.map(|summary| summary.ram_accesses),
+            ),
+            (
+                summary.map(|summary| self.cycles_estimator.estimate(&summary)),
+                prev_summary.map(|summary| self.cycles_estimator.estimate(&summary)),
+            ),
+            (
+                stats.heap().map(|heap| heap.allocations),
+                prev_stats
+                    .and_then(CachegrindStats::heap)
+                    .map(|heap| heap.allocations),
+            ),
+            (
+                stats.heap().map(|heap| heap.bytes),
+                prev_stats
+                    .and_then(CachegrindStats::heap)
+                    .map(|heap| heap.bytes),
+            ),
+        ];
+        for (value, prev_value) in cells {
+            self.write_cell(writer, value, prev_value)?;
+        }
+        writeln!(writer, "</tr>")
+    }
+
+    /// Writes a cell with the value and its diff with the previous value. Diffs within the noise threshold
+    /// are not colored, similar to the terminal output.
+    #[allow(clippy::cast_precision_loss)] // fine for reporting
+    fn write_cell(
+        &self,
+        writer: &mut impl io::Write,
+        value: Option<u64>,
+        prev_value: Option<u64>,
+    ) -> io::Result<()> {
+        let Some(value) = value else {
+            return writeln!(writer, r#"  <td data-value="">–</td>"#);
+        };
+        write!(writer, r#"  <td data-value="{value}">{value}"#)?;
+        if let Some(prev_value) = prev_value {
+            let diff = value.abs_diff(prev_value);
+            let is_noise = diff as f64 <= self.noise_threshold * prev_value as f64;
+            let (class, sign) = match value.cmp(&prev_value) {
+                Ordering::Greater if is_noise => ("noise", "+"),
+                Ordering::Greater => ("regressed", "+"),
+                Ordering::Less if is_noise => ("noise", "-"),
+                Ordering::Less => ("improved", "-"),
+                Ordering::Equal => ("unchanged", "±"),
+            };
+            write!(writer, r#"<span class="diff {class}">{sign}{diff}"#)?;
+            if prev_value > 0 {
+                let percent = diff as f64 * 100.0 / prev_value as f64;
+                write!(writer, " ({sign}{percent:.2}%)")?;
+            }
+            write!(writer, "</span>")?;
+        }
+        writeln!(writer, "</td>")
+    }
+}
+
+fn write_breakdown(
+    writer: &mut impl io::Write,
+    instructions: CachegrindDataPoint,
+    data_reads: CachegrindDataPoint,
+    data_writes: CachegrindDataPoint,
+) -> io::Result<()> {
+    write!(
+        writer,
+        "<table><tr><th></th><th>Total</th><th>L1 hits</th><th>L2/L3 hits</th><th>RAM accesses</th></tr>"
+    )?;
+    let rows = [
+        ("Instr.", instructions),
+        ("Data reads", data_reads),
+        ("Data writes", data_writes),
+    ];
+    for (name, point) in rows {
+        write!(
+            writer,
+            "<tr><td>{name}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            point.total,
+            point.l1_hits(),
+            point.l3_hits(),
+            point.l3_misses
+        )?;
+    }
+    write!(writer, "</table>")
+}
+
+impl Reporter for HtmlReporter {
+    fn new_benchmark(&mut self, id: &BenchmarkId) -> Box<dyn BenchmarkReporter> {
+        Box::new(HtmlBenchmarkReporter {
+            entries: self.entries.clone(),
+            id: id.clone(),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct HtmlBenchmarkReporter {
+    entries: SharedEntries,
+    id: BenchmarkId,
+}
+
+impl BenchmarkReporter for HtmlBenchmarkReporter {
+    fn ok(self: Box<Self>, output: &BenchmarkOutput) {
+        let entry = Entry {
+            id: self.id,
+            output: output.clone(),
+        };
+        self.entries
+            .lock()
+            .expect("entries are poisoned")
+            .push(entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FullCachegrindStats, HeapStats};
+
+    fn output(stats: CachegrindStats, prev_stats: Option<CachegrindStats>) -> BenchmarkOutput {
+        BenchmarkOutput {
+            stats,
+            prev_stats,
+            wall_time: None,
+            is_approximate: false,
+            raw_output_path: None,
+        }
+    }
+
+    #[test]
+    fn writing_html_report() {
+        let mut reporter = HtmlReporter::new(String::new(), CyclesEstimator::default(), 0.0);
+        let point = |total| CachegrindDataPoint {
+            total,
+            l1_misses: total / 10,
+            l3_misses: total / 100,
+        };
+        let full = FullCachegrindStats {
+            instructions: point(1_000),
+            data_reads: point(500),
+            data_writes: point(200),
+        };
+        let prev_full = FullCachegrindStats {
+            instructions: point(1_100),
+            ..full
+        };
+        reporter.new_benchmark(&"fib/<15>".into()).ok(&output(
+            CachegrindStats::Full(full),
+            Some(CachegrindStats::Full(prev_full)),
+        ));
+        let heap = HeapStats {
+            allocations: 3,
+            bytes: 256,
+        };
+        reporter.new_benchmark(&"alloc".into()).ok(&output(
+            CachegrindStats::with_heap(2_000, heap),
+            Some(CachegrindStats::with_heap(1_000, heap)),
+        ));
+        reporter
+            .new_benchmark(&"failed".into())
+            .error(&"cachegrind exited abnormally");
+
+        let mut buffer = vec![];
+        reporter.write_report(&mut buffer).unwrap();
+        let report = String::from_utf8(buffer).unwrap();
+
+        assert!(report.starts_with("<!DOCTYPE html>"), "{report}");
+        assert!(!report.contains("failed"), "{report}");
+        // Entries are sorted by name.
+        let alloc_pos = report.find(r#"<td data-value="alloc">"#).unwrap();
+        let fib_pos = report.find(r#"<td data-value="fib/&lt;15&gt;">"#).unwrap();
+        assert!(alloc_pos < fib_pos, "{report}");
+
+        assert!(
+            report.contains(
+                r#"<td data-value="2000">2000<span class="diff regressed">+1000 (+100.00%)</span></td>"#
+            ),
+            "{report}"
+        );
+        assert!(
+            report.contains(
+                r#"<td data-value="3">3<span class="diff unchanged">±0 (±0.00%)</span></td>"#
+            ),
+            "{report}"
+        );
+        assert!(
+            report.contains(
+                r#"<td data-value="1000">1000<span class="diff improved">-100 (-9.09%)</span></td>"#
+            ),
+            "{report}"
+        );
+        assert!(
+            report.contains("<details><summary>fib/&lt;15&gt;</summary>"),
+            "{report}"
+        );
+        assert!(
+            report.contains(
+                "<tr><td>Data writes</td><td>200</td><td>180</td><td>18</td><td>2</td></tr>"
+            ),
+            "{report}"
+        );
+    }
+
+    #[test]
+    fn html_report_respects_noise_threshold() {
+        let mut reporter = HtmlReporter::new(String::new(), CyclesEstimator::default(), 0.05);
+        reporter.new_benchmark(&"noisy".into()).ok(&output(
+            CachegrindStats::simple(1_020),
+            Some(CachegrindStats::simple(1_000)),
+        ));
+        reporter.new_benchmark(&"regressed".into()).ok(&output(
+            CachegrindStats::simple(1_100),
+            Some(CachegrindStats::simple(1_000)),
+        ));
+
+        let mut buffer = vec![];
+        reporter.write_report(&mut buffer).unwrap();
+        let report = String::from_utf8(buffer).unwrap();
+
+        assert!(
+            report.contains(
+                r#"<td data-value="1020">1020<span class="diff noise">+20 (+2.00%)</span></td>"#
+            ),
+            "{report}"
+        );
+        assert!(
+            report.contains(
+                r#"<td data-value="1100">1100<span class="diff regressed">+100 (+10.00%)</span></td>"#
+            ),
+            "{report}"
+        );
+    }
+
+    #[test]
+    fn html_write_error_is_returned() {
+        let dir = tempfile::tempdir().unwrap();
+        // The report path is a directory, so it cannot be opened as a file.
+        let out_path = dir.path().to_str().unwrap().to_owned();
+        let reporter = HtmlReporter::new(out_path, CyclesEstimator::default(), 0.0);
+        reporter.write_to_file().unwrap_err();
+    }
+}
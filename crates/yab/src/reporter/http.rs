@@ -0,0 +1,150 @@
+//! Reporter implementation posting benchmark results to an HTTP endpoint.
+
+use std::{
+    mem,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use super::{BenchmarkOutput, BenchmarkReporter, PrintingReporter, Reporter};
+use crate::BenchmarkId;
+
+/// Timeout for posting results.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Serialize)]
+struct Entry {
+    #[serde(flatten)]
+    id: BenchmarkId,
+    #[serde(flatten)]
+    output: BenchmarkOutput,
+}
+
+type SharedEntries = Arc<Mutex<Vec<Entry>>>;
+
+/// Buffers benchmark outputs and posts them as a single JSON array once all benchmarks are completed.
+/// Unlike file reporters, failing to post results does not fail the benchmarking run; it is reported
+/// as a warning instead.
+#[derive(Debug)]
+pub(crate) struct HttpReporter {
+    url: String,
+    token: Option<String>,
+    entries: SharedEntries,
+    printer: PrintingReporter,
+}
+
+impl HttpReporter {
+    pub fn new(url: String, token: Option<String>, printer: PrintingReporter) -> Self {
+        Self {
+            url,
+            token,
+            entries: SharedEntries::default(),
+            printer,
+        }
+    }
+
+    fn payload(&self) -> serde_json::Result<String> {
+        let mut entries = mem::take(&mut *self.entries.lock().expect("entries are poisoned"));
+        entries.sort_unstable_by(|entry, other| entry.id.cmp(&other.id));
+        serde_json::to_string(&entries)
+    }
+
+    fn post(&self) -> Result<(), String> {
+        let payload = self
+            .payload()
+            .map_err(|err| format!("failed serializing benchmark results: {err}"))?;
+        let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+        let mut request = agent
+            .post(&self.url)
+            .set("Content-Type", "application/json");
+        if let Some(token) = &self.token {
+            request = request.set("Authorization", &format!("Bearer {token}"));
+        }
+        request
+            .send_string(&payload)
+            .map_err(|err| err.to_string())?;
+        Ok(())
+    }
+}
+
+impl Reporter for HttpReporter {
+    fn new_benchmark(&mut self, id: &BenchmarkId) -> Box<dyn BenchmarkReporter> {
+        Box::new(HttpBenchmarkReporter {
+            entries: self.entries.clone(),
+            id: id.clone(),
+        })
+    }
+
+    fn ok(self: Box<Self>) {
+        if let Err(err) = self.post() {
+            let message = format!("Failed posting benchmark results: {err}");
+            self.printer.report_warning(None, &message);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct HttpBenchmarkReporter {
+    entries: SharedEntries,
+    id: BenchmarkId,
+}
+
+impl BenchmarkReporter for HttpBenchmarkReporter {
+    fn ok(self: Box<Self>, output: &BenchmarkOutput) {
+        let entry = Entry {
+            id: self.id,
+            output: output.clone(),
+        };
+        self.entries
+            .lock()
+            .expect("entries are poisoned")
+            .push(entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{reporter::Verbosity, CachegrindStats};
+
+    #[test]
+    fn serializing_payload() {
+        let printer = PrintingReporter::new(false, Verbosity::Normal);
+        let mut reporter = HttpReporter::new("http://localhost".into(), None, printer);
+        let output = |instructions, prev_instructions: Option<u64>| BenchmarkOutput {
+            stats: CachegrindStats::simple(instructions),
+            prev_stats: prev_instructions.map(CachegrindStats::simple),
+            wall_time: None,
+            is_approximate: false,
+            raw_output_path: None,
+        };
+        reporter
+            .new_benchmark(&"fib/15".into())
+            .ok(&output(1_000, Some(1_100)));
+        let id = BenchmarkId::from("alloc").tag("category", "heap");
+        reporter.new_benchmark(&id).ok(&output(2_000, None));
+
+        let payload: serde_json::Value =
+            serde_json::from_str(&reporter.payload().unwrap()).unwrap();
+        assert_eq!(
+            payload,
+            serde_json::json!([
+                {
+                    "id": "alloc",
+                    "tags": { "category": "heap" },
+                    "stats": { "instructions": 2_000 },
+                    "prev_stats": null,
+                },
+                {
+                    "id": "fib/15",
+                    "stats": { "instructions": 1_000 },
+                    "prev_stats": { "instructions": 1_100 },
+                },
+            ])
+        );
+        // Entries are consumed by posting.
+        assert_eq!(reporter.payload().unwrap(), "[]");
+    }
+}
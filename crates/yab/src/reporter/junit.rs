@@ -0,0 +1,212 @@
+//! Reporter implementation writing test outcomes as a `JUnit` XML document.
+
+use std::{
+    any::Any,
+    fmt, io,
+    io::Write as _,
+    mem,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use super::{BenchmarkReporter, MarkupEscaped, Reporter, TestReporter};
+use crate::BenchmarkId;
+
+#[derive(Debug)]
+struct TestCase {
+    id: BenchmarkId,
+    duration: Duration,
+    failure: Option<String>,
+}
+
+type SharedCases = Arc<Mutex<Vec<TestCase>>>;
+
+/// Buffers test outcomes and writes them as a `JUnit` XML document once all tests are completed.
+#[derive(Debug)]
+pub(crate) struct JunitReporter {
+    out_path: String,
+    cases: SharedCases,
+}
+
+impl JunitReporter {
+    pub fn new(out_path: String) -> Self {
+        Self {
+            out_path,
+            cases: SharedCases::default(),
+        }
+    }
+
+    fn write_to_file(&self) {
+        let out_path = &self.out_path;
+        let write_result = super::create_report_file(out_path).and_then(|file| {
+            let mut writer = io::BufWriter::new(file);
+            self.write_report(&mut writer)?;
+            writer.flush()
+        });
+        write_result.unwrap_or_else(|err| {
+            panic!("Failed writing JUnit report to `{out_path}`: {err}");
+        });
+    }
+
+    fn write_report(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        let cases = mem::take(&mut *self.cases.lock().expect("test cases are poisoned"));
+        let failures = cases.iter().filter(|case| case.failure.is_some()).count();
+        let total_duration: Duration = cases.iter().map(|case| case.duration).sum();
+
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<testsuite name="yab" tests="{tests}" failures="{failures}" time="{time:.3}">"#,
+            tests = cases.len(),
+            time = total_duration.as_secs_f64()
+        )?;
+        for case in &cases {
+            write!(
+                writer,
+                r#"  <testcase name="{name}" classname="{class}" time="{time:.3}""#,
+                name = MarkupEscaped(&case.id),
+                class = MarkupEscaped(case.id.location_file()),
+                time = case.duration.as_secs_f64()
+            )?;
+            if let Some(failure) = &case.failure {
+                writeln!(writer, ">")?;
+                writeln!(
+                    writer,
+                    r#"    <failure message="{message}">{message}</failure>"#,
+                    message = MarkupEscaped(failure)
+                )?;
+                writeln!(writer, "  </testcase>")?;
+            } else {
+                writeln!(writer, "/>")?;
+            }
+        }
+        writeln!(writer, "</testsuite>")
+    }
+}
+
+impl Reporter for JunitReporter {
+    fn error(&mut self, _error: &dyn fmt::Display) {
+        // Test failures are reported as an error, but the report is still useful in this case.
+        self.write_to_file();
+    }
+
+    fn new_test(&mut self, id: &BenchmarkId) -> Box<dyn TestReporter> {
+        Box::new(JunitTestReporter {
+            cases: self.cases.clone(),
+            id: id.clone(),
+            started_at: Instant::now(),
+        })
+    }
+
+    fn new_benchmark(&mut self, _id: &BenchmarkId) -> Box<dyn BenchmarkReporter> {
+        Box::new(())
+    }
+
+    fn ok(self: Box<Self>) {
+        self.write_to_file();
+    }
+}
+
+#[derive(Debug)]
+struct JunitTestReporter {
+    cases: SharedCases,
+    id: BenchmarkId,
+    started_at: Instant,
+}
+
+impl JunitTestReporter {
+    fn push(self, failure: Option<String>) {
+        let case = TestCase {
+            id: self.id,
+            duration: self.started_at.elapsed(),
+            failure,
+        };
+        self.cases
+            .lock()
+            .expect("test cases are poisoned")
+            .push(case);
+    }
+}
+
+impl TestReporter for JunitTestReporter {
+    fn ok(self: Box<Self>) {
+        self.push(None);
+    }
+
+    fn fail(self: Box<Self>, panic_data: &dyn Any) {
+        let message = if let Some(&message) = panic_data.downcast_ref::<&str>() {
+            message.to_owned()
+        } else if let Some(message) = panic_data.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "test panicked".to_owned()
+        };
+        self.push(Some(message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writing_junit_report() {
+        let mut reporter = JunitReporter::new(String::new());
+        reporter.new_test(&BenchmarkId::from("fib_short")).ok();
+        let panic_data: Box<dyn Any> = Box::new("assertion `left == right` failed: 1 < 2");
+        reporter
+            .new_test(&BenchmarkId::new("fib", 15))
+            .fail(panic_data.as_ref());
+
+        let mut buffer = vec![];
+        reporter.write_report(&mut buffer).unwrap();
+        let report = String::from_utf8(buffer).unwrap();
+        let lines: Vec<_> = report.lines().collect();
+
+        assert_eq!(lines[0], r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        assert!(
+            lines[1].starts_with(r#"<testsuite name="yab" tests="2" failures="1" time=""#),
+            "{report}"
+        );
+        assert!(
+            lines[2].starts_with(r#"  <testcase name="fib_short" classname=""#),
+            "{report}"
+        );
+        assert!(lines[2].contains("junit.rs"), "{report}");
+        assert!(lines[2].ends_with("/>"), "{report}");
+        assert!(
+            lines[3].starts_with(r#"  <testcase name="fib/15""#),
+            "{report}"
+        );
+        assert_eq!(
+            lines[4],
+            "    <failure message=\"assertion `left == right` failed: 1 &lt; 2\">\
+             assertion `left == right` failed: 1 &lt; 2</failure>"
+        );
+        assert_eq!(lines[5], "  </testcase>");
+        assert_eq!(lines[6], "</testsuite>");
+    }
+
+    #[test]
+    fn writing_junit_report_with_control_chars() {
+        let mut reporter = JunitReporter::new(String::new());
+        let panic_data: Box<dyn Any> =
+            Box::new("\x1b[31mfailed\x1b[0m: got \0 at\tindex 1".to_owned());
+        reporter
+            .new_test(&BenchmarkId::from("colored"))
+            .fail(panic_data.as_ref());
+
+        let mut buffer = vec![];
+        reporter.write_report(&mut buffer).unwrap();
+        let report = String::from_utf8(buffer).unwrap();
+        assert!(
+            !report.contains(|ch: char| ch.is_control() && ch != '\n' && ch != '\t'),
+            "{report:?}"
+        );
+        let expected_message = "\u{fffd}[31mfailed\u{fffd}[0m: got \u{fffd} at\tindex 1";
+        assert!(
+            report.contains(&format!("<failure message=\"{expected_message}\">")),
+            "{report:?}"
+        );
+    }
+}
@@ -1,19 +1,68 @@
 //! Benchmark reporting.
 
-use std::{any::Any, fmt};
+use std::{
+    any::Any,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "http")]
+pub(crate) use self::http::HttpReporter;
 pub(crate) use self::{
-    printer::{PrintingReporter, Verbosity},
+    html::HtmlReporter,
+    junit::JunitReporter,
+    printer::{CyclesEstimator, PrintingReporter, Verbosity},
+    processor::ProcessingReporter,
     seq::SeqReporter,
 };
 use crate::{BenchmarkId, CachegrindStats};
 
+mod html;
+#[cfg(feature = "http")]
+mod http;
+mod junit;
 mod printer;
+mod processor;
 mod seq;
 
+/// Escapes special chars when displaying a value inside HTML / XML text or attributes. Control chars other than
+/// whitespace (e.g., from ANSI escape sequences in panic messages) cannot be represented in XML 1.0 even if escaped,
+/// so they are replaced with U+FFFD.
+struct MarkupEscaped<T>(T);
+
+impl<T: fmt::Display> fmt::Display for MarkupEscaped<T> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for ch in self.0.to_string().chars() {
+            match ch {
+                '&' => formatter.write_str("&amp;")?,
+                '<' => formatter.write_str("&lt;")?,
+                '>' => formatter.write_str("&gt;")?,
+                '"' => formatter.write_str("&quot;")?,
+                // `&apos;` isn't defined in HTML 4, so a numeric reference is used instead.
+                '\'' => formatter.write_str("&#39;")?,
+                '\t' | '\n' | '\r' => fmt::Write::write_char(formatter, ch)?,
+                _ if ch.is_control() || ch == '\u{fffe}' || ch == '\u{ffff}' => {
+                    fmt::Write::write_char(formatter, char::REPLACEMENT_CHARACTER)?;
+                }
+                _ => fmt::Write::write_char(formatter, ch)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Creates a file for a report, together with its parent directories if necessary.
+fn create_report_file(path: &str) -> io::Result<fs::File> {
+    if let Some(parent_dir) = Path::new(path).parent() {
+        fs::create_dir_all(parent_dir)?;
+    }
+    fs::File::create(path)
+}
+
 /// Output produced by the [`Bencher`](crate::Bencher) for a single benchmark.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -23,6 +72,70 @@ pub struct BenchmarkOutput {
     pub stats: CachegrindStats,
     /// Previous stats for the benchmark.
     pub prev_stats: Option<CachegrindStats>,
+    /// Wall-clock time spent collecting stats for the benchmark (i.e., running it under `cachegrind`).
+    /// Not available if the stats were loaded from a previous run (e.g., when using `--print`).
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub wall_time: Option<Duration>,
+    /// Whether the stats are approximate, i.e. collected in a single run without subtracting the baseline
+    /// (`--fast` command-line option). Approximate stats include the benchmark setup and fixed overhead,
+    /// so they are not comparable to precise ones.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "std::ops::Not::not")
+    )]
+    pub is_approximate: bool,
+    /// Path to the raw output of the full `cachegrind` run for the benchmark, e.g. to post-process it
+    /// with `cg_annotate`. Beware that the output covers the entire benchmark execution, including setup
+    /// and warm-up iterations.
+    ///
+    /// Only set if the output is retained after the benchmark is run (i.e., not with `--fast`, `--compare-only`
+    /// or `--keep-cachegrind-output` other than `always`). Not set if the stats were loaded from a previous run
+    /// (e.g., when using `--print`).
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub raw_output_path: Option<PathBuf>,
+}
+
+/// Processor of successful benchmark outputs, e.g. to make assertions on the collected stats.
+///
+/// Processors are called in the main process once the stats for a benchmark are collected
+/// (or loaded, if using `--print`), and are not called in the test mode. A processor may panic
+/// to fail the benchmarking run.
+///
+/// The trait is implemented for closures with the matching signature.
+///
+/// # Examples
+///
+/// ```no_run
+/// use yab::{reporter::BenchmarkOutput, BenchmarkId, Bencher};
+///
+/// fn benchmarks(bencher: &mut Bencher) {
+///     bencher.with_processor(|id: &BenchmarkId, output: &BenchmarkOutput| {
+///         if id.to_string().starts_with("cheap/") {
+///             assert!(output.stats.total_instructions() < 1_000, "{id} is too expensive");
+///         }
+///     });
+///     bencher.bench("cheap/sum", || (0..100_u64).sum::<u64>());
+/// }
+/// # yab::main!(benchmarks);
+/// ```
+pub trait BenchmarkProcessor: Send {
+    /// Processes output for a single benchmark.
+    fn process_benchmark(&mut self, id: &BenchmarkId, output: &BenchmarkOutput);
+}
+
+impl<F> BenchmarkProcessor for F
+where
+    F: FnMut(&BenchmarkId, &BenchmarkOutput) + Send,
+{
+    fn process_benchmark(&mut self, id: &BenchmarkId, output: &BenchmarkOutput) {
+        self(id, output);
+    }
 }
 
 /// Reporter for benchmarking output that allows to extend or modify benchmarking logic.
@@ -36,6 +149,17 @@ pub trait Reporter: fmt::Debug {
         // do nothing
     }
 
+    /// Signals that benchmarking has started, providing the total number of benchmarks to be run.
+    /// Called once before [`Self::new_benchmark()`] is called for any benchmark. Only called if benchmarks
+    /// are collected before running them (i.e., with the `--collect-first` command-line arg); by default,
+    /// benchmarks are run as soon as they are defined, so the total number isn't known beforehand.
+    /// Not called in the dry run (i.e., with `--dry-run`) since no benchmarks are run.
+    ///
+    /// The default implementation does nothing.
+    fn run_started(&mut self, total: usize) {
+        // do nothing
+    }
+
     /// Initializes a test with the specified ID.
     fn new_test(&mut self, id: &BenchmarkId) -> Box<dyn TestReporter> {
         Box::new(())
@@ -91,6 +215,20 @@ pub trait BenchmarkReporter: Send + fmt::Debug {
         // do nothing
     }
 
+    /// Reports the benchmark calibration results: the estimated number of instructions executed
+    /// on a single iteration, and the number of `iterations` chosen for the benchmark.
+    /// If `iterations` is equal to `--max-iterations`, the benchmark may be under-warmed.
+    ///
+    /// The per-iteration estimate is the difference between the baseline and calibration runs divided by
+    /// the difference in their iteration counts, so that it excludes constant overhead (e.g., process startup).
+    /// Hence, this method is called after the baseline run. If the baseline run is skipped (i.e., with a single
+    /// iteration), the estimate is the instruction count of the calibration run, including the overhead.
+    ///
+    /// The default implementation does nothing.
+    fn calibrated(&mut self, per_iter_instructions: u64, iterations: u64) {
+        // do nothing
+    }
+
     /// Reports output for a single benchmark.
     fn ok(self: Box<Self>, output: &BenchmarkOutput);
 
@@ -108,3 +246,10 @@ pub trait BenchmarkReporter: Send + fmt::Debug {
         // do nothing
     }
 }
+
+/// No-op implementation.
+impl BenchmarkReporter for () {
+    fn ok(self: Box<Self>, _output: &BenchmarkOutput) {
+        // do nothing
+    }
+}
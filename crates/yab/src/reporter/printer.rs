@@ -3,8 +3,8 @@
 use std::{
     any::Any,
     cmp::Ordering,
-    fmt, io, ops,
-    sync::{Arc, Mutex},
+    fmt, io, mem, ops,
+    sync::{Arc, Mutex, PoisonError, RwLock},
     time::Instant,
 };
 
@@ -15,15 +15,20 @@ use anes::{
 use super::{BenchmarkOutput, Reporter};
 use crate::{
     cachegrind::{AccessSummary, CachegrindStats},
+    options::ListFormat,
+    utils::iterations_str,
     BenchmarkId, FullCachegrindStats,
 };
 
 /// Full width of the label column.
 const LABEL_WIDTH: usize = 15;
-/// Full width of the number column.
+/// Default full width of the number column. May be increased to fit large values if outputs are deferred.
 const NUMBER_WIDTH: usize = 16;
-/// Width of the diff column (not including percentages).
+/// Default width of the diff column (not including percentages). May be increased similarly to `NUMBER_WIDTH`.
 const DIFF_WIDTH: usize = 12;
+/// Default relative change up to which a difference is considered noise. Used to classify benchmarks
+/// as regressed / improved in the summary and with `--changed-only`, and to color diffs.
+const DEFAULT_NOISE_THRESHOLD: f64 = 0.01;
 
 #[derive(Debug, Clone, Copy)]
 enum Checkmark {
@@ -65,6 +70,9 @@ struct LinePrinter<W> {
     inner: W,
     styling: bool,
     style_nesting: usize,
+    number_width: usize,
+    diff_width: usize,
+    noise_threshold: f64,
 }
 
 impl<W: io::Write> LinePrinter<W> {
@@ -133,14 +141,17 @@ impl<W: io::Write> LinePrinter<W> {
         self.print(format_args!(" {args}\n"));
     }
 
-    fn print_warning(&mut self, id: &BenchmarkId, args: fmt::Arguments<'_>) {
+    fn print_warning(&mut self, id: Option<&BenchmarkId>, args: fmt::Arguments<'_>) {
         self.bold()
             .bg(Color::Yellow)
             .fg(Color::White)
             .print_str(" WARN:");
-        self.print_str(" ");
-        self.print_id(id, true);
-        self.print(format_args!(": {args}\n"));
+        if let Some(id) = id {
+            self.print_str(" ");
+            self.print_id(id, true);
+            self.print_str(":");
+        }
+        self.print(format_args!(" {args}\n"));
     }
 
     fn print_error(&mut self, id: Option<&BenchmarkId>, args: fmt::Arguments<'_>) {
@@ -156,38 +167,60 @@ impl<W: io::Write> LinePrinter<W> {
         self.print(format_args!(" {args}\n"));
     }
 
-    fn print_id(&mut self, id: &BenchmarkId, print_location: bool) {
+    /// Prints the benchmark ID. In the verbose mode, also prints tags and the location of the benchmark.
+    fn print_id(&mut self, id: &BenchmarkId, verbose: bool) {
         let BenchmarkId {
             name,
             args,
             location,
+            tags,
         } = id;
+        let file = id.location_file();
 
         self.print(format_args!("{name}"));
         if let Some(args) = args {
             self.print(format_args!("/{args}"));
         }
-        if print_location {
+        if verbose {
+            if !tags.is_empty() {
+                let tags: Vec<_> = tags
+                    .iter()
+                    .map(|(key, value)| format!("{key}={value}"))
+                    .collect();
+                self.dimmed().print(format_args!(" [{}]", tags.join(", ")));
+            }
             self.dimmed()
-                .print(format_args!(" @ {}:{}", location.file(), location.line()));
+                .print(format_args!(" @ {file}:{}", location.line()));
         }
     }
 
     #[allow(clippy::cast_precision_loss, clippy::cast_possible_wrap)] // fine for reporting
     fn print_diff(&mut self, new: u64, old: u64) {
+        let diff_width = self.diff_width;
+        let change = Change::for_values(new, old, self.noise_threshold);
         match new.cmp(&old) {
             Ordering::Less => {
-                self.fg(Color::Green).print(format_args!(
-                    " {:>+DIFF_WIDTH$} ({:+.2}%)",
+                let mut styled = if change == Change::Improved {
+                    self.fg(Color::Green)
+                } else {
+                    self.dimmed()
+                };
+                styled.print(format_args!(
+                    " {:>+diff_width$} ({:+.2}%)",
                     new as i64 - old as i64,
-                    (old - new) as f32 * -100.0 / old as f32
+                    (old - new) as f64 * -100.0 / old as f64
                 ));
             }
             Ordering::Greater => {
-                self.fg(Color::Red).print(format_args!(
-                    " {:>+DIFF_WIDTH$} ({:+.2}%)",
+                let mut styled = if change == Change::Regressed {
+                    self.fg(Color::Red)
+                } else {
+                    self.dimmed()
+                };
+                styled.print(format_args!(
+                    " {:>+diff_width$} ({:+.2}%)",
                     new - old,
-                    (new - old) as f32 * 100.0 / old as f32
+                    (new - old) as f64 * 100.0 / old as f64
                 ));
             }
             Ordering::Equal => { /* don't print anything */ }
@@ -195,15 +228,32 @@ impl<W: io::Write> LinePrinter<W> {
     }
 
     fn print_row(&mut self, label: &str, last: bool, new: u64, old: Option<u64>) {
+        self.print_row_with_contribution(label, last, new, old, None);
+    }
+
+    /// Prints a row optionally annotated with its contribution (in percent) to the estimated cycles.
+    fn print_row_with_contribution(
+        &mut self,
+        label: &str,
+        last: bool,
+        new: u64,
+        old: Option<u64>,
+        contribution: Option<f64>,
+    ) {
         const ROW_LABEL_WIDTH: usize = LABEL_WIDTH - 2;
 
         let line = if last { '└' } else { '├' };
+        let number_width = self.number_width;
         self.print(format_args!(
-            "{line} {label:<ROW_LABEL_WIDTH$} {new:>NUMBER_WIDTH$}"
+            "{line} {label:<ROW_LABEL_WIDTH$} {new:>number_width$}"
         ));
         if let Some(old) = old {
             self.print_diff(new, old);
         }
+        if let Some(contribution) = contribution {
+            self.dimmed()
+                .print(format_args!(" ({contribution:.0}% of cycles)"));
+        }
         self.print_str("\n");
     }
 
@@ -211,8 +261,9 @@ impl<W: io::Write> LinePrinter<W> {
         const DETAIL_LABEL_WIDTH: usize = LABEL_WIDTH - 4;
 
         let line = if last { '└' } else { '├' };
+        let number_width = self.number_width;
         self.print(format_args!(
-            "│ {line} {label:<DETAIL_LABEL_WIDTH$} {new:>NUMBER_WIDTH$}"
+            "│ {line} {label:<DETAIL_LABEL_WIDTH$} {new:>number_width$}"
         ));
         if let Some(old) = old {
             self.print_diff(new, old);
@@ -252,10 +303,209 @@ pub(crate) enum Verbosity {
     Verbose,
 }
 
+/// Benchmark outputs buffered until all benchmarks are completed.
+type DeferredOutputs = Arc<Mutex<Vec<(BenchmarkId, BenchmarkOutput)>>>;
+
+/// Change of a benchmark output compared to the previous output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Regressed,
+    Improved,
+    Unchanged,
+    NoData,
+}
+
+impl Change {
+    /// Classifies a benchmark output based on all summary stats that can be printed for it (instructions,
+    /// heap stats, cache access summary and estimated cycles), regardless of the verbosity. The output
+    /// is considered regressed if any stat regressed beyond the noise threshold, and improved if any stat
+    /// improved beyond the threshold and none regressed.
+    fn new(
+        output: &BenchmarkOutput,
+        noise_threshold: f64,
+        cycles_estimator: &CyclesEstimator,
+    ) -> Self {
+        let Some(prev_stats) = &output.prev_stats else {
+            return Self::NoData;
+        };
+
+        let mut values = vec![(
+            output.stats.total_instructions(),
+            prev_stats.total_instructions(),
+        )];
+        match (&output.stats, prev_stats) {
+            (CachegrindStats::Heap { heap, .. }, _) => {
+                if let Some(old_heap) = prev_stats.heap() {
+                    values.push((heap.allocations, old_heap.allocations));
+                    values.push((heap.bytes, old_heap.bytes));
+                }
+            }
+            (CachegrindStats::Full(stats), CachegrindStats::Full(old_stats)) => {
+                let summary = AccessSummary::from(*stats);
+                let old_summary = AccessSummary::from(*old_stats);
+                values.extend([
+                    (summary.l1_hits, old_summary.l1_hits),
+                    (summary.l3_hits, old_summary.l3_hits),
+                    (summary.ram_accesses, old_summary.ram_accesses),
+                    (
+                        cycles_estimator.estimate(&summary),
+                        cycles_estimator.estimate(&old_summary),
+                    ),
+                ]);
+            }
+            _ => { /* only instructions are comparable */ }
+        }
+
+        values
+            .into_iter()
+            .map(|(new, old)| Self::for_values(new, old, noise_threshold))
+            .fold(Self::Unchanged, |acc, change| match (acc, change) {
+                (Self::Regressed, _) | (_, Self::Regressed) => Self::Regressed,
+                (Self::Improved, _) | (_, Self::Improved) => Self::Improved,
+                _ => Self::Unchanged,
+            })
+    }
+
+    /// Classifies a change of a single stat. This is used both for classifying outputs and for coloring diffs,
+    /// so that they are consistent.
+    #[allow(clippy::cast_precision_loss)] // fine for classification
+    fn for_values(new: u64, old: u64, noise_threshold: f64) -> Self {
+        let is_noise = (new.abs_diff(old) as f64) <= noise_threshold * old as f64;
+        match new.cmp(&old) {
+            Ordering::Greater if !is_noise => Self::Regressed,
+            Ordering::Less if !is_noise => Self::Improved,
+            _ => Self::Unchanged,
+        }
+    }
+}
+
+/// Aggregate benchmark outcomes printed once all benchmarks are completed.
+#[derive(Debug, Default)]
+struct Summary {
+    regressed: usize,
+    improved: usize,
+    unchanged: usize,
+    no_data: usize,
+    failed: usize,
+    /// Number of unchanged benchmarks whose output was not printed.
+    suppressed: usize,
+}
+
+impl Summary {
+    fn push(&mut self, change: Change) {
+        match change {
+            Change::Regressed => self.regressed += 1,
+            Change::Improved => self.improved += 1,
+            Change::Unchanged => self.unchanged += 1,
+            Change::NoData => self.no_data += 1,
+        }
+    }
+
+    fn total(&self) -> usize {
+        self.regressed + self.improved + self.unchanged + self.no_data + self.failed
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.total();
+        let noun = if total == 1 {
+            "benchmark"
+        } else {
+            "benchmarks"
+        };
+        write!(
+            formatter,
+            "{total} {noun}, {} regressed, {} improved, {} unchanged",
+            self.regressed, self.improved, self.unchanged
+        )?;
+        if self.no_data > 0 {
+            write!(formatter, ", {} without previous data", self.no_data)?;
+        }
+        if self.failed > 0 {
+            write!(formatter, ", {} failed", self.failed)?;
+        }
+        if self.suppressed > 0 {
+            write!(formatter, " ({} unchanged not shown)", self.suppressed)?;
+        }
+        Ok(())
+    }
+}
+
+type EstimateCycles = dyn Fn(&AccessSummary) -> u64 + Send + Sync;
+
+/// Formula to estimate CPU cycles, shared between the bencher and the printing reporter.
+/// If not set, [`AccessSummary::estimated_cycles()`] is used.
+#[derive(Clone, Default)]
+pub(crate) struct CyclesEstimator(Arc<RwLock<Option<Arc<EstimateCycles>>>>);
+
+impl fmt::Debug for CyclesEstimator {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let is_custom = self.get().is_some();
+        formatter
+            .debug_struct("CyclesEstimator")
+            .field("is_custom", &is_custom)
+            .finish()
+    }
+}
+
+impl CyclesEstimator {
+    fn get(&self) -> Option<Arc<EstimateCycles>> {
+        let estimator = self.0.read().unwrap_or_else(PoisonError::into_inner);
+        estimator.clone()
+    }
+
+    pub fn set(&self, estimator: impl Fn(&AccessSummary) -> u64 + Send + Sync + 'static) {
+        let mut guard = self.0.write().unwrap_or_else(PoisonError::into_inner);
+        *guard = Some(Arc::new(estimator));
+    }
+
+    pub fn estimate(&self, summary: &AccessSummary) -> u64 {
+        match self.get() {
+            Some(estimator) => estimator(summary),
+            None => summary.estimated_cycles(),
+        }
+    }
+
+    /// Computes contributions (in percent) of L1 hits, L2/L3 hits and RAM accesses to the estimated cycles.
+    /// Each contribution is estimated by applying the formula to the summary with only the corresponding
+    /// component retained; this is precise for linear formulas like the default one.
+    #[allow(clippy::cast_precision_loss)] // fine for reporting
+    fn contributions(&self, summary: &AccessSummary) -> [f64; 3] {
+        let total = self.estimate(summary).max(1) as f64;
+        let empty = AccessSummary {
+            instructions: 0,
+            l1_hits: 0,
+            l3_hits: 0,
+            ram_accesses: 0,
+        };
+        let parts = [
+            AccessSummary {
+                l1_hits: summary.l1_hits,
+                ..empty
+            },
+            AccessSummary {
+                l3_hits: summary.l3_hits,
+                ..empty
+            },
+            AccessSummary {
+                ram_accesses: summary.ram_accesses,
+                ..empty
+            },
+        ];
+        parts.map(|part| self.estimate(&part) as f64 * 100.0 / total)
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct PrintingReporter<W = io::Stderr> {
     verbosity: Verbosity,
     line_printer: Arc<Mutex<LinePrinter<W>>>,
+    deferred_outputs: Option<DeferredOutputs>,
+    changed_only: bool,
+    show_contributions: bool,
+    summary: Arc<Mutex<Summary>>,
+    cycles_estimator: CyclesEstimator,
 }
 
 impl<W> Clone for PrintingReporter<W> {
@@ -263,6 +513,11 @@ impl<W> Clone for PrintingReporter<W> {
         Self {
             verbosity: self.verbosity,
             line_printer: self.line_printer.clone(),
+            deferred_outputs: self.deferred_outputs.clone(),
+            changed_only: self.changed_only,
+            show_contributions: self.show_contributions,
+            summary: self.summary.clone(),
+            cycles_estimator: self.cycles_estimator.clone(),
         }
     }
 }
@@ -273,23 +528,120 @@ impl PrintingReporter {
             inner: io::stderr(),
             styling,
             style_nesting: 0,
+            number_width: NUMBER_WIDTH,
+            diff_width: DIFF_WIDTH,
+            noise_threshold: DEFAULT_NOISE_THRESHOLD,
         };
         Self {
             verbosity,
             line_printer: Arc::new(Mutex::new(line_printer)),
+            deferred_outputs: None,
+            changed_only: false,
+            show_contributions: false,
+            summary: Arc::default(),
+            cycles_estimator: CyclesEstimator::default(),
         }
     }
 
-    pub fn report_list_item(id: &BenchmarkId) {
-        println!("{id}: benchmark");
+    /// Writes the list of benchmarks in the specified format.
+    pub fn report_list(
+        ids: &[BenchmarkId],
+        format: ListFormat,
+        writer: &mut impl io::Write,
+    ) -> io::Result<()> {
+        match format {
+            ListFormat::Terse => {
+                for id in ids {
+                    writeln!(writer, "{id}: benchmark")?;
+                }
+            }
+            ListFormat::Json => {
+                writeln!(writer, "[")?;
+                for (i, id) in ids.iter().enumerate() {
+                    let tags = id.tags.iter().map(|(key, value)| {
+                        format!("{}: {}", JsonEscaped(key), JsonEscaped(value))
+                    });
+                    let tags: Vec<_> = tags.collect();
+                    let separator = if i + 1 == ids.len() { "" } else { "," };
+                    writeln!(
+                        writer,
+                        "  {{ \"name\": {name}, \"file\": {file}, \"line\": {line}, \"tags\": {{{tags}}} }}{separator}",
+                        name = JsonEscaped(id),
+                        file = JsonEscaped(id.location_file()),
+                        line = id.location.line(),
+                        tags = tags.join(", ")
+                    )?;
+                }
+                writeln!(writer, "]")?;
+            }
+        }
+        Ok(())
     }
 }
 
 impl<W: io::Write> PrintingReporter<W> {
+    /// Defers printing benchmark outputs until all benchmarks are completed. Outputs will be printed
+    /// sorted by the benchmark ID.
+    pub(crate) fn defer_outputs(&mut self) {
+        self.deferred_outputs = Some(DeferredOutputs::default());
+    }
+
+    /// Annotates cache access rows with their contribution to the estimated cycles.
+    pub(crate) fn show_contributions(&mut self) {
+        self.show_contributions = true;
+    }
+
+    /// Sets the relative change up to which differences are considered noise (e.g., 0.01 for 1%).
+    pub(crate) fn set_noise_threshold(&mut self, threshold: f64) {
+        self.lock_printer().noise_threshold = threshold;
+    }
+
+    /// Only prints outputs for benchmarks that have changed compared to the previous run
+    /// (or have no previous data). Unchanged benchmarks are only counted in the summary.
+    pub(crate) fn print_changed_only(&mut self) {
+        self.changed_only = true;
+    }
+
+    /// Returns a handle to the formula used to estimate CPU cycles.
+    pub(crate) fn cycles_estimator(&self) -> CyclesEstimator {
+        self.cycles_estimator.clone()
+    }
+
     fn lock_printer(&self) -> impl ops::DerefMut<Target = LinePrinter<W>> + '_ {
         self.line_printer.lock().expect("line printer is poisoned")
     }
 
+    fn lock_summary(&self) -> impl ops::DerefMut<Target = Summary> + '_ {
+        self.summary.lock().expect("summary is poisoned")
+    }
+
+    /// Returns the maximum value printed in the output rows (not including verbose details,
+    /// which are bounded by the summary values).
+    fn max_printed_value(&self, output: &BenchmarkOutput) -> u64 {
+        let values = |stats: &CachegrindStats| match *stats {
+            CachegrindStats::Simple { instructions } => instructions,
+            CachegrindStats::Heap { instructions, heap } => {
+                instructions.max(heap.allocations).max(heap.bytes)
+            }
+            CachegrindStats::Full(full) => {
+                let summary = AccessSummary::from(full);
+                let cycles = self.cycles_estimator.estimate(&summary);
+                [
+                    summary.instructions,
+                    summary.l1_hits,
+                    summary.l3_hits,
+                    summary.ram_accesses,
+                    cycles,
+                ]
+                .into_iter()
+                .max()
+                .unwrap_or(0)
+            }
+        };
+        let prev_value = output.prev_stats.as_ref().map_or(0, values);
+        values(&output.stats).max(prev_value)
+    }
+
     pub(crate) fn report_debug(&self, args: fmt::Arguments<'_>) {
         if self.verbosity < Verbosity::Verbose {
             return;
@@ -301,9 +653,46 @@ impl<W: io::Write> PrintingReporter<W> {
         self.lock_printer().print_error(id, format_args!("{err}"));
     }
 
-    fn report_warning(&self, id: &BenchmarkId, err: &dyn fmt::Display) {
+    pub(crate) fn report_warning(&self, id: Option<&BenchmarkId>, err: &dyn fmt::Display) {
         self.lock_printer().print_warning(id, format_args!("{err}"));
     }
+
+    /// Prints deferred benchmark outputs (if any) and the summary. Called once benchmarking is completed,
+    /// either successfully or with an error.
+    fn print_outputs_and_summary(&self) {
+        if let Some(deferred_outputs) = &self.deferred_outputs {
+            let mut outputs =
+                mem::take(&mut *deferred_outputs.lock().expect("outputs are poisoned"));
+            outputs.sort_by(|(id, _), (other_id, _)| id.cmp(other_id));
+
+            // Widen number columns to fit the largest printed value, so that the rows remain aligned.
+            let max_value = outputs
+                .iter()
+                .map(|(_, output)| self.max_printed_value(output))
+                .max();
+            let max_len = max_value.unwrap_or(0).to_string().len();
+            {
+                let mut printer = self.lock_printer();
+                printer.number_width = NUMBER_WIDTH.max(max_len);
+                // +1 for the sign
+                printer.diff_width = DIFF_WIDTH.max(max_len + 1);
+            }
+
+            for (bench_id, output) in outputs {
+                let reporter = BenchmarkReporter {
+                    parent: self.clone(),
+                    bench_id,
+                };
+                reporter.print_output(&output);
+            }
+        }
+
+        // The summary is empty in the test and list modes, since no benchmarks are reported.
+        let summary = self.lock_summary();
+        if self.verbosity > Verbosity::Quiet && summary.total() > 0 {
+            self.lock_printer().print(format_args!("{}\n", *summary));
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -336,7 +725,6 @@ impl<W: io::Write> super::TestReporter for TestReporter<W> {
 struct BenchmarkReporter<W> {
     parent: PrintingReporter<W>,
     bench_id: BenchmarkId,
-    started_at: Option<Instant>,
 }
 
 impl<W: io::Write> BenchmarkReporter<W> {
@@ -349,6 +737,9 @@ impl<W: io::Write> BenchmarkReporter<W> {
         let parent = &self.parent;
         let summary = AccessSummary::from(stats);
         let old_summary = old_stats.map(AccessSummary::from);
+        let contributions = parent
+            .show_contributions
+            .then(|| parent.cycles_estimator.contributions(&summary));
 
         printer.print_row(
             "Instructions",
@@ -358,11 +749,12 @@ impl<W: io::Write> BenchmarkReporter<W> {
         );
 
         if parent.verbosity >= Verbosity::Normal {
-            printer.print_row(
+            printer.print_row_with_contribution(
                 "L1 hits",
                 false,
                 summary.l1_hits,
                 old_summary.map(|old| old.l1_hits),
+                contributions.map(|[l1, ..]| l1),
             );
             if parent.verbosity >= Verbosity::Verbose {
                 printer.print_details(
@@ -371,11 +763,12 @@ impl<W: io::Write> BenchmarkReporter<W> {
                 );
             }
 
-            printer.print_row(
+            printer.print_row_with_contribution(
                 "L2/L3 hits",
                 false,
                 summary.l3_hits,
                 old_summary.map(|old| old.l3_hits),
+                contributions.map(|[_, l3, _]| l3),
             );
             if parent.verbosity >= Verbosity::Verbose {
                 printer.print_details(
@@ -384,11 +777,12 @@ impl<W: io::Write> BenchmarkReporter<W> {
                 );
             }
 
-            printer.print_row(
+            printer.print_row_with_contribution(
                 "RAM accesses",
                 false,
                 summary.ram_accesses,
                 old_summary.map(|old| old.ram_accesses),
+                contributions.map(|[.., ram]| ram),
             );
             if parent.verbosity >= Verbosity::Verbose {
                 printer.print_details(
@@ -401,38 +795,28 @@ impl<W: io::Write> BenchmarkReporter<W> {
         printer.print_row(
             "Est. cycles",
             true,
-            summary.estimated_cycles(),
-            old_summary.map(|old| old.estimated_cycles()),
+            parent.cycles_estimator.estimate(&summary),
+            old_summary.map(|old| parent.cycles_estimator.estimate(&old)),
         );
     }
-}
-
-impl<W: io::Write + fmt::Debug + Send> super::BenchmarkReporter for BenchmarkReporter<W> {
-    fn start_execution(&mut self) {
-        self.started_at = Some(Instant::now());
-    }
-
-    fn baseline_computed(&mut self, stats: &CachegrindStats) {
-        if self.parent.verbosity < Verbosity::Verbose {
-            return;
-        }
-
-        let mut printer = self.parent.lock_printer();
-        printer.print_checkbox(Checkmark::InProgress);
-        printer.print_id(&self.bench_id, true);
-        let instr = stats.total_instructions();
-        printer.print(format_args!(": captured baseline ({instr} instructions)\n"));
-    }
 
-    fn ok(self: Box<Self>, output: &BenchmarkOutput) {
-        let BenchmarkOutput { stats, prev_stats } = output;
+    fn print_output(&self, output: &BenchmarkOutput) {
+        let BenchmarkOutput {
+            stats,
+            prev_stats,
+            wall_time,
+            is_approximate,
+            raw_output_path: _,
+        } = output;
 
         let mut printer = self.parent.lock_printer();
         printer.print_checkbox(Checkmark::Pass);
         printer.print_id(&self.bench_id, self.parent.verbosity >= Verbosity::Verbose);
-        if let Some(started_at) = self.started_at {
-            let latency = started_at.elapsed();
-            printer.dimmed().print(format_args!(" ({latency:?})"));
+        if let Some(wall_time) = wall_time {
+            printer.dimmed().print(format_args!(" ({wall_time:?})"));
+        }
+        if *is_approximate {
+            printer.fg(Color::Yellow).print_str(" [approximate]");
         }
         printer.print_str("\n");
 
@@ -442,7 +826,33 @@ impl<W: io::Write + fmt::Debug + Send> super::BenchmarkReporter for BenchmarkRep
                 printer.print_row("Instructions", true, instructions, old_instructions);
                 return;
             }
-            (_, Some(CachegrindStats::Simple { instructions: old })) => {
+            (CachegrindStats::Heap { instructions, heap }, _) => {
+                let old_instructions = prev_stats.as_ref().map(CachegrindStats::total_instructions);
+                let old_heap = prev_stats.as_ref().and_then(CachegrindStats::heap);
+                printer.print_row("Instructions", false, instructions, old_instructions);
+                printer.print_row(
+                    "Allocations",
+                    false,
+                    heap.allocations,
+                    old_heap.map(|old| old.allocations),
+                );
+                printer.print_row(
+                    "Alloc. bytes",
+                    true,
+                    heap.bytes,
+                    old_heap.map(|old| old.bytes),
+                );
+                return;
+            }
+            (
+                _,
+                Some(
+                    CachegrindStats::Simple { instructions: old }
+                    | CachegrindStats::Heap {
+                        instructions: old, ..
+                    },
+                ),
+            ) => {
                 printer.print_row("Instructions", true, stats.total_instructions(), Some(old));
                 return;
             }
@@ -454,12 +864,61 @@ impl<W: io::Write + fmt::Debug + Send> super::BenchmarkReporter for BenchmarkRep
 
         self.full_diff(&mut printer, stats, prev_stats);
     }
+}
+
+impl<W: io::Write + fmt::Debug + Send> super::BenchmarkReporter for BenchmarkReporter<W> {
+    fn baseline_computed(&mut self, stats: &CachegrindStats) {
+        if self.parent.verbosity < Verbosity::Verbose {
+            return;
+        }
+
+        let mut printer = self.parent.lock_printer();
+        printer.print_checkbox(Checkmark::InProgress);
+        printer.print_id(&self.bench_id, true);
+        let instr = stats.total_instructions();
+        printer.print(format_args!(": captured baseline ({instr} instructions)\n"));
+    }
+
+    fn calibrated(&mut self, per_iter_instructions: u64, iterations: u64) {
+        if self.parent.verbosity < Verbosity::Verbose {
+            return;
+        }
+
+        let mut printer = self.parent.lock_printer();
+        printer.print_checkbox(Checkmark::InProgress);
+        printer.print_id(&self.bench_id, true);
+        printer.print(format_args!(
+            ": calibrated ({per_iter_instructions} instructions / iteration, {})\n",
+            iterations_str(iterations)
+        ));
+    }
+
+    fn ok(self: Box<Self>, output: &BenchmarkOutput) {
+        let noise_threshold = self.parent.lock_printer().noise_threshold;
+        let change = Change::new(output, noise_threshold, &self.parent.cycles_estimator);
+        {
+            let mut summary = self.parent.lock_summary();
+            summary.push(change);
+            if self.parent.changed_only && change == Change::Unchanged {
+                summary.suppressed += 1;
+                return;
+            }
+        }
+
+        if let Some(deferred_outputs) = &self.parent.deferred_outputs {
+            let mut deferred_outputs = deferred_outputs.lock().expect("outputs are poisoned");
+            deferred_outputs.push((self.bench_id, output.clone()));
+        } else {
+            self.print_output(output);
+        }
+    }
 
     fn warning(&mut self, warning: &dyn fmt::Display) {
-        self.parent.report_warning(&self.bench_id, warning);
+        self.parent.report_warning(Some(&self.bench_id), warning);
     }
 
     fn error(self: Box<Self>, error: &dyn fmt::Display) {
+        self.parent.lock_summary().failed += 1;
         self.parent.report_error(Some(&self.bench_id), error);
     }
 }
@@ -469,6 +928,8 @@ where
     W: io::Write + fmt::Debug + Send + 'static,
 {
     fn error(&mut self, error: &dyn fmt::Display) {
+        // Outputs of the completed benchmarks are still relevant.
+        self.print_outputs_and_summary();
         self.report_error(None, error);
     }
 
@@ -491,9 +952,12 @@ where
         Box::new(BenchmarkReporter {
             parent: self.clone(),
             bench_id: id.clone(),
-            started_at: None,
         })
     }
+
+    fn ok(self: Box<Self>) {
+        self.print_outputs_and_summary();
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -529,20 +993,51 @@ impl FullCachegrindStats {
     }
 }
 
+/// Displays a value as a JSON string, escaping special chars.
+struct JsonEscaped<T>(T);
+
+impl<T: fmt::Display> fmt::Display for JsonEscaped<T> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("\"")?;
+        for ch in self.0.to_string().chars() {
+            match ch {
+                '"' => formatter.write_str("\\\"")?,
+                '\\' => formatter.write_str("\\\\")?,
+                '\n' => formatter.write_str("\\n")?,
+                '\r' => formatter.write_str("\\r")?,
+                '\t' => formatter.write_str("\\t")?,
+                ch if ch.is_control() => write!(formatter, "\\u{:04x}", u32::from(ch))?,
+                _ => fmt::Write::write_char(formatter, ch)?,
+            }
+        }
+        formatter.write_str("\"")
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use super::*;
-    use crate::cachegrind::{CachegrindDataPoint, FullCachegrindStats};
+    use crate::cachegrind::{CachegrindDataPoint, FullCachegrindStats, HeapStats};
 
     fn mock_reporter(verbosity: Verbosity) -> PrintingReporter<Vec<u8>> {
         let line_printer = LinePrinter {
             inner: vec![],
             styling: false,
             style_nesting: 0,
+            number_width: NUMBER_WIDTH,
+            diff_width: DIFF_WIDTH,
+            noise_threshold: DEFAULT_NOISE_THRESHOLD,
         };
         PrintingReporter {
             verbosity,
             line_printer: Arc::new(Mutex::new(line_printer)),
+            deferred_outputs: None,
+            changed_only: false,
+            show_contributions: false,
+            summary: Arc::default(),
+            cycles_estimator: CyclesEstimator::default(),
         }
     }
 
@@ -576,21 +1071,277 @@ mod tests {
     fn reporting_basic_stats() {
         let mut reporter = mock_reporter(Verbosity::Normal);
         let stats = CachegrindStats::Simple { instructions: 123 };
-        let mut bench = reporter.new_benchmark(&BenchmarkId::from("test"));
-        bench.start_execution();
-        bench.ok(&BenchmarkOutput {
-            stats,
-            prev_stats: None,
-        });
+        reporter
+            .new_benchmark(&BenchmarkId::from("test"))
+            .ok(&BenchmarkOutput {
+                stats,
+                prev_stats: None,
+                wall_time: Some(Duration::from_millis(10)),
+                is_approximate: false,
+                raw_output_path: None,
+            });
 
         let buffer = extract_buffer(reporter);
         let lines: Vec<_> = buffer.lines().collect();
         assert_eq!(lines.len(), 2, "{buffer}");
-        assert!(lines[0].starts_with("[√] test ("), "{buffer}");
+        assert_eq!(lines[0], "[√] test (10ms)");
         assert!(!lines[0].contains("printer.rs"), "{buffer}");
         assert_eq!(lines[1], "└ Instructions               123");
     }
 
+    #[test]
+    fn reporting_sorted_stats() {
+        let mut reporter = mock_reporter(Verbosity::Normal);
+        reporter.defer_outputs();
+        for (name, instructions) in [("b", 2), ("c", 3), ("a", 1)] {
+            reporter
+                .new_benchmark(&BenchmarkId::from(name))
+                .ok(&BenchmarkOutput {
+                    stats: CachegrindStats::Simple { instructions },
+                    prev_stats: None,
+                    wall_time: None,
+                    is_approximate: false,
+                    raw_output_path: None,
+                });
+        }
+        assert!(reporter.lock_printer().inner.is_empty());
+
+        Box::new(reporter.clone()).ok();
+        let buffer = extract_buffer(reporter);
+        let lines: Vec<_> = buffer.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "[√] a",
+                "└ Instructions                 1",
+                "[√] b",
+                "└ Instructions                 2",
+                "[√] c",
+                "└ Instructions                 3",
+                "3 benchmarks, 0 regressed, 0 improved, 0 unchanged, 3 without previous data"
+            ]
+        );
+    }
+
+    #[test]
+    fn printing_deferred_outputs_on_error() {
+        let mut reporter = mock_reporter(Verbosity::Normal);
+        reporter.defer_outputs();
+        reporter
+            .new_benchmark(&BenchmarkId::from("a"))
+            .ok(&BenchmarkOutput {
+                stats: CachegrindStats::Simple { instructions: 1 },
+                prev_stats: None,
+                wall_time: None,
+                is_approximate: false,
+                raw_output_path: None,
+            });
+        reporter
+            .new_benchmark(&BenchmarkId::from("b"))
+            .error(&"oops");
+
+        reporter.clone().error(&"benchmarking failed");
+        let buffer = extract_buffer(reporter);
+        let lines: Vec<_> = buffer.lines().collect();
+        // Benchmark errors are printed immediately; successful outputs are deferred.
+        assert!(lines[0].contains("oops"), "{buffer}");
+        assert_eq!(lines[1], "[√] a", "{buffer}");
+        let summary_pos = lines
+            .iter()
+            .position(|line| line.starts_with("2 benchmarks"))
+            .unwrap_or_else(|| panic!("{buffer}"));
+        let error_pos = lines
+            .iter()
+            .position(|line| line.contains("benchmarking failed"))
+            .unwrap_or_else(|| panic!("{buffer}"));
+        assert!(summary_pos < error_pos, "{buffer}");
+    }
+
+    #[test]
+    fn widening_columns_for_sorted_stats() {
+        let mut reporter = mock_reporter(Verbosity::Normal);
+        reporter.defer_outputs();
+        let outputs = [
+            ("a", 12_345_678_901_234_567_890, 12_345_678_901_234_567_000),
+            ("b", 2, 2),
+        ];
+        for (name, instructions, prev_instructions) in outputs {
+            reporter
+                .new_benchmark(&BenchmarkId::from(name))
+                .ok(&BenchmarkOutput {
+                    stats: CachegrindStats::Simple { instructions },
+                    prev_stats: Some(CachegrindStats::Simple {
+                        instructions: prev_instructions,
+                    }),
+                    wall_time: None,
+                    is_approximate: false,
+                    raw_output_path: None,
+                });
+        }
+
+        Box::new(reporter.clone()).ok();
+        let buffer = extract_buffer(reporter);
+        let lines: Vec<_> = buffer.lines().collect();
+        assert_eq!(
+            lines[..4],
+            [
+                "[√] a",
+                "└ Instructions  12345678901234567890                  +890 (+0.00%)",
+                "[√] b",
+                "└ Instructions                     2",
+            ]
+        );
+    }
+
+    #[test]
+    fn reporting_summary() {
+        let mut reporter = mock_reporter(Verbosity::Quiet);
+        let outputs = [
+            (120, Some(100)),
+            (80, Some(100)),
+            (1_001, Some(1_000)),
+            (100, None),
+        ];
+        for (i, (instructions, prev_instructions)) in outputs.into_iter().enumerate() {
+            reporter
+                .new_benchmark(&BenchmarkId::new("test", i))
+                .ok(&BenchmarkOutput {
+                    stats: CachegrindStats::Simple { instructions },
+                    prev_stats: prev_instructions
+                        .map(|instructions| CachegrindStats::Simple { instructions }),
+                    wall_time: None,
+                    is_approximate: false,
+                    raw_output_path: None,
+                });
+        }
+        reporter
+            .new_benchmark(&BenchmarkId::from("failed"))
+            .error(&"oops");
+
+        let summary = reporter.lock_summary().to_string();
+        assert_eq!(
+            summary,
+            "5 benchmarks, 1 regressed, 1 improved, 1 unchanged, 1 without previous data, 1 failed"
+        );
+
+        // The summary should not be printed in the quiet mode.
+        Box::new(reporter.clone()).ok();
+        let buffer = extract_buffer(reporter);
+        assert!(!buffer.contains("5 benchmarks"), "{buffer}");
+    }
+
+    #[test]
+    fn dimming_diffs_within_noise_threshold() {
+        let mut reporter = mock_reporter(Verbosity::Normal);
+        reporter.lock_printer().styling = true;
+        reporter.set_noise_threshold(0.05);
+        for (name, instructions) in [("noise", 103), ("regressed", 120)] {
+            reporter
+                .new_benchmark(&BenchmarkId::from(name))
+                .ok(&BenchmarkOutput {
+                    stats: CachegrindStats::Simple { instructions },
+                    prev_stats: Some(CachegrindStats::Simple { instructions: 100 }),
+                    wall_time: None,
+                    is_approximate: false,
+                    raw_output_path: None,
+                });
+        }
+
+        let summary = reporter.lock_summary().to_string();
+        assert_eq!(
+            summary,
+            "2 benchmarks, 1 regressed, 0 improved, 1 unchanged"
+        );
+        let buffer = extract_buffer(reporter);
+        let red = SetForegroundColor(Color::Red).to_string();
+        let (noise_output, regressed_output) = buffer.split_once("regressed").unwrap();
+        assert!(noise_output.contains("+3"), "{buffer}");
+        assert!(!noise_output.contains(&red), "{buffer}");
+        assert!(regressed_output.contains(&red), "{buffer}");
+    }
+
+    #[test]
+    fn classifying_changes_by_all_stats() {
+        let stats = mock_stats();
+        let mut more_misses = stats;
+        more_misses.data_reads.l3_misses = 20;
+        let heap = |allocations| HeapStats {
+            allocations,
+            bytes: 1_024,
+        };
+        let outputs = [
+            // Same instructions, but more RAM accesses
+            (
+                CachegrindStats::Full(more_misses),
+                CachegrindStats::Full(stats),
+            ),
+            // Same instructions, but fewer allocations
+            (
+                CachegrindStats::with_heap(100, heap(5)),
+                CachegrindStats::with_heap(100, heap(10)),
+            ),
+            (CachegrindStats::Full(stats), CachegrindStats::Full(stats)),
+        ];
+
+        let mut reporter = mock_reporter(Verbosity::Quiet);
+        reporter.set_noise_threshold(0.01);
+        for (i, (stats, prev_stats)) in outputs.into_iter().enumerate() {
+            reporter
+                .new_benchmark(&BenchmarkId::new("test", i))
+                .ok(&BenchmarkOutput {
+                    stats,
+                    prev_stats: Some(prev_stats),
+                    wall_time: None,
+                    is_approximate: false,
+                    raw_output_path: None,
+                });
+        }
+
+        let summary = reporter.lock_summary().to_string();
+        assert_eq!(
+            summary,
+            "3 benchmarks, 1 regressed, 1 improved, 1 unchanged"
+        );
+    }
+
+    #[test]
+    fn reporting_changed_stats_only() {
+        let mut reporter = mock_reporter(Verbosity::Normal);
+        reporter.print_changed_only();
+        let outputs = [
+            ("regressed", 120, Some(100)),
+            ("unchanged", 1_001, Some(1_000)),
+            ("new", 100, None),
+        ];
+        for (name, instructions, prev_instructions) in outputs {
+            reporter
+                .new_benchmark(&BenchmarkId::from(name))
+                .ok(&BenchmarkOutput {
+                    stats: CachegrindStats::Simple { instructions },
+                    prev_stats: prev_instructions
+                        .map(|instructions| CachegrindStats::Simple { instructions }),
+                    wall_time: None,
+                    is_approximate: false,
+                    raw_output_path: None,
+                });
+        }
+
+        Box::new(reporter.clone()).ok();
+        let buffer = extract_buffer(reporter);
+        let lines: Vec<_> = buffer.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "[√] regressed",
+                "└ Instructions               120          +20 (+20.00%)",
+                "[√] new",
+                "└ Instructions               100",
+                "3 benchmarks, 1 regressed, 0 improved, 1 unchanged, 1 without previous data \
+                 (1 unchanged not shown)"
+            ]
+        );
+    }
+
     #[test]
     fn reporting_basic_stats_with_diff() {
         let mut reporter = mock_reporter(Verbosity::Normal);
@@ -601,6 +1352,9 @@ mod tests {
             .ok(&BenchmarkOutput {
                 stats,
                 prev_stats: Some(prev_stats),
+                wall_time: None,
+                is_approximate: false,
+                raw_output_path: None,
             });
 
         let buffer = extract_buffer(reporter);
@@ -613,6 +1367,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn reporting_diff_for_large_values() {
+        let mut reporter = mock_reporter(Verbosity::Normal);
+        // The precise diff is ≈0.6250000557%; with `f32` arithmetic, it would be displayed as 0.62%.
+        let stats = CachegrindStats::Simple {
+            instructions: 124_228_394,
+        };
+        let prev_stats = CachegrindStats::Simple {
+            instructions: 123_456_789,
+        };
+        reporter
+            .new_benchmark(&BenchmarkId::from("test"))
+            .ok(&BenchmarkOutput {
+                stats,
+                prev_stats: Some(prev_stats),
+                wall_time: None,
+                is_approximate: false,
+                raw_output_path: None,
+            });
+
+        let buffer = extract_buffer(reporter);
+        let lines: Vec<_> = buffer.lines().collect();
+        assert_eq!(lines.len(), 2, "{buffer}");
+        assert!(lines[1].ends_with("+771605 (+0.63%)"), "{buffer}");
+    }
+
+    #[test]
+    fn reporting_heap_stats_with_diff() {
+        let mut reporter = mock_reporter(Verbosity::Normal);
+        let stats = CachegrindStats::Heap {
+            instructions: 120,
+            heap: HeapStats {
+                allocations: 3,
+                bytes: 96,
+            },
+        };
+        let prev_stats = CachegrindStats::Heap {
+            instructions: 100,
+            heap: HeapStats {
+                allocations: 4,
+                bytes: 96,
+            },
+        };
+        reporter
+            .new_benchmark(&BenchmarkId::from("test"))
+            .ok(&BenchmarkOutput {
+                stats,
+                prev_stats: Some(prev_stats),
+                wall_time: None,
+                is_approximate: false,
+                raw_output_path: None,
+            });
+
+        let buffer = extract_buffer(reporter);
+        let lines: Vec<_> = buffer.lines().collect();
+        assert_eq!(lines.len(), 4, "{buffer}");
+        assert_eq!(lines[0], "[√] test");
+        assert_eq!(
+            lines[1],
+            "├ Instructions               120          +20 (+20.00%)"
+        );
+        assert_eq!(
+            lines[2],
+            "├ Allocations                  3           -1 (-25.00%)"
+        );
+        assert_eq!(lines[3], "└ Alloc. bytes                96");
+    }
+
     #[test]
     fn reporting_full_stats() {
         let mut reporter = mock_reporter(Verbosity::Normal);
@@ -622,6 +1444,9 @@ mod tests {
             .ok(&BenchmarkOutput {
                 stats,
                 prev_stats: None,
+                wall_time: None,
+                is_approximate: false,
+                raw_output_path: None,
             });
 
         let buffer = extract_buffer(reporter);
@@ -635,6 +1460,52 @@ mod tests {
         assert_eq!(lines[5], "└ Est. cycles               1350");
     }
 
+    #[test]
+    fn reporting_full_stats_with_contributions() {
+        let mut reporter = mock_reporter(Verbosity::Normal);
+        reporter.show_contributions();
+        let stats = CachegrindStats::Full(mock_stats());
+        reporter
+            .new_benchmark(&BenchmarkId::from("test"))
+            .ok(&BenchmarkOutput {
+                stats,
+                prev_stats: None,
+                wall_time: None,
+                is_approximate: false,
+                raw_output_path: None,
+            });
+
+        let buffer = extract_buffer(reporter);
+        let lines: Vec<_> = buffer.lines().collect();
+        assert_eq!(lines.len(), 6, "{buffer}");
+        assert_eq!(lines[2], "├ L1 hits                    250 (19% of cycles)");
+        assert_eq!(lines[3], "├ L2/L3 hits                  80 (30% of cycles)");
+        assert_eq!(lines[4], "├ RAM accesses                20 (52% of cycles)");
+        assert_eq!(lines[5], "└ Est. cycles               1350");
+    }
+
+    #[test]
+    fn reporting_full_stats_with_custom_cycles_estimator() {
+        let mut reporter = mock_reporter(Verbosity::Normal);
+        reporter
+            .cycles_estimator()
+            .set(|summary| summary.l1_hits + 10 * summary.l3_hits + 100 * summary.ram_accesses);
+        let stats = CachegrindStats::Full(mock_stats());
+        reporter
+            .new_benchmark(&BenchmarkId::from("test"))
+            .ok(&BenchmarkOutput {
+                stats,
+                prev_stats: None,
+                wall_time: None,
+                is_approximate: false,
+                raw_output_path: None,
+            });
+
+        let buffer = extract_buffer(reporter);
+        let lines: Vec<_> = buffer.lines().collect();
+        assert_eq!(lines[5], "└ Est. cycles               3050", "{buffer}");
+    }
+
     #[test]
     fn reporting_full_stats_verbosely() {
         let mut reporter = mock_reporter(Verbosity::Verbose);
@@ -644,6 +1515,9 @@ mod tests {
             .ok(&BenchmarkOutput {
                 stats,
                 prev_stats: None,
+                wall_time: None,
+                is_approximate: false,
+                raw_output_path: None,
             });
 
         let buffer = extract_buffer(reporter);
@@ -667,6 +1541,56 @@ mod tests {
         assert_eq!(*lines.last().unwrap(), "└ Est. cycles               1350");
     }
 
+    #[test]
+    fn reporting_tags_verbosely() {
+        let mut reporter = mock_reporter(Verbosity::Verbose);
+        let id = BenchmarkId::from("test")
+            .tag("owner", "team-a")
+            .tag("category", "parsing");
+        reporter.new_benchmark(&id).ok(&BenchmarkOutput {
+            stats: CachegrindStats::Simple { instructions: 123 },
+            prev_stats: None,
+            wall_time: None,
+            is_approximate: false,
+            raw_output_path: None,
+        });
+
+        let buffer = extract_buffer(reporter);
+        let lines: Vec<_> = buffer.lines().collect();
+        assert!(
+            lines[1].starts_with("[√] test [category=parsing, owner=team-a] @"),
+            "{buffer}"
+        );
+    }
+
+    #[test]
+    fn listing_benchmarks() {
+        let ids = [
+            BenchmarkId::from("fib/15"),
+            BenchmarkId::from("quoted \"name\"").tag("owner", "team-a"),
+        ];
+        let mut buffer = vec![];
+        PrintingReporter::report_list(&ids, ListFormat::Terse, &mut buffer).unwrap();
+        let buffer = String::from_utf8(buffer).unwrap();
+        assert_eq!(buffer, "fib/15: benchmark\nquoted \"name\": benchmark\n");
+
+        let mut buffer = vec![];
+        PrintingReporter::report_list(&ids, ListFormat::Json, &mut buffer).unwrap();
+        let list: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(
+            list,
+            serde_json::json!([
+                { "name": "fib/15", "file": file!(), "line": ids[0].location.line(), "tags": {} },
+                {
+                    "name": "quoted \"name\"",
+                    "file": file!(),
+                    "line": ids[1].location.line(),
+                    "tags": { "owner": "team-a" },
+                },
+            ])
+        );
+    }
+
     #[test]
     fn reporting_full_stats_with_diff() {
         let mut reporter = mock_reporter(Verbosity::Normal);
@@ -679,6 +1603,9 @@ mod tests {
             .ok(&BenchmarkOutput {
                 stats,
                 prev_stats: Some(CachegrindStats::Full(prev_stats)),
+                wall_time: None,
+                is_approximate: false,
+                raw_output_path: None,
             });
 
         let buffer = extract_buffer(reporter);
@@ -0,0 +1,100 @@
+//! Adapter of `BenchmarkProcessor`s to reporters.
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use super::{BenchmarkOutput, BenchmarkProcessor, BenchmarkReporter, Reporter};
+use crate::BenchmarkId;
+
+/// Reporter passing successful benchmark outputs to a [`BenchmarkProcessor`].
+pub(crate) struct ProcessingReporter<P> {
+    processor: Arc<Mutex<P>>,
+}
+
+impl<P> fmt::Debug for ProcessingReporter<P> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("ProcessingReporter")
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P: BenchmarkProcessor + 'static> ProcessingReporter<P> {
+    pub fn new(processor: P) -> Self {
+        Self {
+            processor: Arc::new(Mutex::new(processor)),
+        }
+    }
+}
+
+impl<P: BenchmarkProcessor + 'static> Reporter for ProcessingReporter<P> {
+    fn new_benchmark(&mut self, id: &BenchmarkId) -> Box<dyn BenchmarkReporter> {
+        Box::new(ProcessingBenchmarkReporter {
+            processor: self.processor.clone(),
+            id: id.clone(),
+        })
+    }
+}
+
+struct ProcessingBenchmarkReporter<P> {
+    processor: Arc<Mutex<P>>,
+    id: BenchmarkId,
+}
+
+impl<P> fmt::Debug for ProcessingBenchmarkReporter<P> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("ProcessingBenchmarkReporter")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P: BenchmarkProcessor> BenchmarkReporter for ProcessingBenchmarkReporter<P> {
+    fn ok(self: Box<Self>, output: &BenchmarkOutput) {
+        // The processor may have panicked for another benchmark; this shouldn't affect other benchmarks.
+        let mut processor = self
+            .processor
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        processor.process_benchmark(&self.id, output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CachegrindStats;
+
+    impl BenchmarkProcessor for Vec<(String, u64)> {
+        fn process_benchmark(&mut self, id: &BenchmarkId, output: &BenchmarkOutput) {
+            self.push((id.to_string(), output.stats.total_instructions()));
+        }
+    }
+
+    #[test]
+    fn processing_benchmark_outputs() {
+        let mut reporter = ProcessingReporter::new(Vec::<(String, u64)>::new());
+        // Errors should not be passed to the processor.
+        reporter.new_benchmark(&"failed".into()).error(&"oops");
+
+        for (name, instructions) in [("fib/10", 100), ("fib/20", 200)] {
+            let output = BenchmarkOutput {
+                stats: CachegrindStats::simple(instructions),
+                prev_stats: None,
+                wall_time: None,
+                is_approximate: false,
+                raw_output_path: None,
+            };
+            reporter.new_benchmark(&name.into()).ok(&output);
+        }
+
+        let outputs = reporter.processor.lock().unwrap();
+        assert_eq!(
+            *outputs,
+            [("fib/10".to_owned(), 100), ("fib/20".to_owned(), 200)]
+        );
+    }
+}
@@ -23,6 +23,12 @@ impl Reporter for SeqReporter {
         }
     }
 
+    fn run_started(&mut self, total: usize) {
+        for reporter in &mut self.0 {
+            reporter.run_started(total);
+        }
+    }
+
     fn new_test(&mut self, id: &BenchmarkId) -> Box<dyn TestReporter> {
         struct Seq(Vec<Box<dyn TestReporter>>);
 
@@ -61,6 +67,12 @@ impl Reporter for SeqReporter {
                 }
             }
 
+            fn calibrated(&mut self, per_iter_instructions: u64, iterations: u64) {
+                for reporter in &mut self.0 {
+                    reporter.calibrated(per_iter_instructions, iterations);
+                }
+            }
+
             fn ok(self: Box<Self>, output: &BenchmarkOutput) {
                 for reporter in self.0 {
                     reporter.ok(output);
@@ -1,4 +1,20 @@
-use std::sync::{Arc, Condvar, Mutex};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, Condvar, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Formats the number of iterations with the correctly pluralized noun.
+pub(crate) fn iterations_str(iterations: u64) -> String {
+    let noun = if iterations == 1 {
+        "iteration"
+    } else {
+        "iterations"
+    };
+    format!("{iterations} {noun}")
+}
 
 #[derive(Debug)]
 #[must_use = "released on drop"]
@@ -49,6 +65,51 @@ impl Semaphore {
     }
 }
 
+/// Temporary directory removed on drop. The directory is only accessible by the current user (on Unix), and its name
+/// is unique to the process; creating the directory fails if it already exists, so it cannot be hijacked.
+#[derive(Debug)]
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    const MAX_ATTEMPTS: u32 = 16;
+
+    pub fn new(prefix: &str) -> io::Result<Self> {
+        let temp_dir = env::temp_dir();
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.subsec_nanos());
+        let mut attempt = 0;
+        loop {
+            let name = format!("{prefix}{}-{nanos:x}-{attempt}", process::id());
+            let path = temp_dir.join(name);
+            match builder.create(&path) {
+                Ok(()) => return Ok(Self(path)),
+                Err(err)
+                    if err.kind() == io::ErrorKind::AlreadyExists
+                        && attempt + 1 < Self::MAX_ATTEMPTS =>
+                {
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.0).ok();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
@@ -81,4 +142,18 @@ mod tests {
             thread.join().unwrap();
         }
     }
+
+    #[test]
+    fn creating_temp_dir() {
+        let dir = TempDir::new("yab-test-").unwrap();
+        let path = dir.path().to_owned();
+        assert!(path.is_dir());
+        fs::write(path.join("test.txt"), "test").unwrap();
+
+        let other_dir = TempDir::new("yab-test-").unwrap();
+        assert_ne!(other_dir.path(), path);
+
+        drop(dir);
+        assert!(!path.exists());
+    }
 }
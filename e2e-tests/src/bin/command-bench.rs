@@ -0,0 +1,14 @@
+//! Benchmark executable with an external command benchmark.
+
+use std::{env, process::Command};
+
+use yab::Bencher;
+
+fn benchmarks(bencher: &mut Bencher) {
+    // The command is spawned in another working dir, so that `cachegrind` output paths must not depend on it.
+    let mut command = Command::new("true");
+    command.current_dir(env::temp_dir());
+    bencher.bench_command("command", &command);
+}
+
+yab::main!(benchmarks);
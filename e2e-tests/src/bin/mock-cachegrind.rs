@@ -83,9 +83,14 @@ fn main() {
         args.find_map(|arg| Some(arg.strip_prefix("--cachegrind-out-file=")?.to_owned()));
     let out_file_path = out_file_path.expect("output file is not provided");
 
-    // Args provided to bench binary have rigid structure.
     let args_to_bench_binary: Vec<_> = args.collect();
-    assert_eq!(args_to_bench_binary[1], "--cachegrind-instrument");
+    if args_to_bench_binary.get(1).map(String::as_str) != Some("--cachegrind-instrument") {
+        // External command benchmark; it has constant stats.
+        write_stats(&out_file_path, &args_to_bench_binary, CONST_OVERHEAD);
+        return;
+    }
+
+    // Args provided to bench binary have rigid structure.
     let iter_count: u64 = args_to_bench_binary[2]
         .parse()
         .expect("invalid iteration count");
@@ -113,7 +118,15 @@ fn main() {
         thread::sleep(Duration::MAX);
     }
 
-    let file = fs::File::create(&out_file_path).expect("failed creating output file");
+    write_stats(&out_file_path, &args_to_bench_binary, full_stats);
+}
+
+fn write_stats(
+    out_file_path: &str,
+    args_to_bench_binary: &[String],
+    full_stats: FullCachegrindStats,
+) {
+    let file = fs::File::create(out_file_path).expect("failed creating output file");
     let mut writer = io::BufWriter::new(file);
     writeln!(&mut writer, "cmd: {}", args_to_bench_binary.join(" ")).unwrap();
     writeln!(
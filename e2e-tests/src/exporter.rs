@@ -16,9 +16,17 @@ type SharedOutputs = Arc<Mutex<HashMap<String, BenchmarkOutput>>>;
 #[derive(Debug, Default)]
 pub(crate) struct BenchmarkExporter {
     outputs: SharedOutputs,
+    total_benchmarks: Option<usize>,
+    started_benchmarks: usize,
 }
 
 impl Reporter for BenchmarkExporter {
+    fn run_started(&mut self, total: usize) {
+        assert!(self.total_benchmarks.is_none(), "run started twice");
+        assert_eq!(self.started_benchmarks, 0, "run started after benchmarks");
+        self.total_benchmarks = Some(total);
+    }
+
     fn new_benchmark(&mut self, id: &BenchmarkId) -> Box<dyn BenchmarkReporter> {
         #[derive(Debug)]
         struct Entry(SharedOutputs, String);
@@ -30,10 +38,21 @@ impl Reporter for BenchmarkExporter {
             }
         }
 
+        self.started_benchmarks += 1;
+        // The total number of benchmarks is only known with `--collect-first`.
+        if let Some(total) = self.total_benchmarks {
+            assert!(
+                self.started_benchmarks <= total,
+                "{id} exceeds total benchmarks"
+            );
+        }
         Box::new(Entry(self.outputs.clone(), id.to_string()))
     }
 
     fn ok(self: Box<Self>) {
+        if let Some(total) = self.total_benchmarks {
+            assert_eq!(self.started_benchmarks, total, "not all benchmarks started");
+        }
         let Ok(out_path) = env::var(EXPORTER_OUTPUT_VAR) else {
             return;
         };
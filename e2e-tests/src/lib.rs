@@ -8,15 +8,13 @@
 )]
 
 use rand::{rngs::SmallRng, Rng, SeedableRng};
-use yab::{black_box, Bencher, BenchmarkId};
+use yab::{black_box, black_box_drop, Bencher, BenchmarkId};
 
 use crate::exporter::BenchmarkExporter;
 pub use crate::exporter::EXPORTER_OUTPUT_VAR;
 
 mod exporter;
 
-const RNG_SEED: u64 = 123;
-
 fn fibonacci(n: u64) -> u64 {
     match n {
         0 | 1 => 1,
@@ -59,8 +57,19 @@ pub fn main(bencher: &mut Bencher) {
             FibGuard(20)
         });
     });
+    // Only dropping the guard should be measured
+    bencher.bench_with_drop("guard/drop", || {
+        fibonacci(black_box(10));
+        FibGuard(20)
+    });
+
+    // The computed value is unused, but should still be captured.
+    bencher.bench_with_capture("black_box_drop", |capture| {
+        let _guard = capture.start();
+        black_box_drop(fibonacci(black_box(10)));
+    });
 
-    let mut rng = SmallRng::seed_from_u64(RNG_SEED);
+    let mut rng = SmallRng::seed_from_u64(bencher.seed());
     let random_bytes: Vec<usize> = (0..10_000_000).map(|_| rng.gen()).collect();
 
     for len in [1_000_000, 10_000_000] {
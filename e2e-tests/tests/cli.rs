@@ -90,6 +90,7 @@ fn test_config(sequential: bool) -> (TestConfig<StdShell>, TestLock) {
         .with_env("COLOR", "always")
         .with_env("CACHEGRIND_WRAPPER", MOCK_CACHEGRIND_PATH)
         .with_env("CACHEGRIND_OUT_DIR", &target_path)
+        .with_env("CACHEGRIND_ALLOW_ASLR", "true")
         .with_io_timeout(Duration::from_secs(1));
     if sequential {
         shell_options = shell_options.with_env("CACHEGRIND_JOBS", "1");
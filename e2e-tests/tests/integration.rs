@@ -17,6 +17,7 @@ use yab_e2e_tests::EXPORTER_OUTPUT_VAR;
 
 const EXE_PATH: &str = env!("CARGO_BIN_EXE_yab-e2e-tests");
 const MOCK_CACHEGRIND_PATH: &str = env!("CARGO_BIN_EXE_mock-cachegrind");
+const COMMAND_BENCH_PATH: &str = env!("CARGO_BIN_EXE_command-bench");
 
 #[derive(Debug, Deserialize)]
 struct AllStats {
@@ -36,6 +37,8 @@ const EXPECTED_BENCH_NAMES: &[&str] = &[
     "fib/25",
     "fib_capture",
     "guard",
+    "guard/drop",
+    "black_box_drop",
     "random_walk/1000000",
     "random_walk/10000000",
 ];
@@ -119,6 +122,29 @@ fn testing_with_regex_filter() {
     );
 }
 
+#[test]
+fn testing_with_junit_report() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let junit_path = temp_dir.path().join("junit.xml");
+    let output = Command::new(EXE_PATH)
+        .arg("--junit")
+        .arg(&junit_path)
+        .arg("fib/")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report = fs::read_to_string(&junit_path).unwrap();
+    assert!(
+        report.contains(r#"<testsuite name="yab" tests="3" failures="0""#),
+        "{report}"
+    );
+    for name in ["fib/15", "fib/20", "fib/25"] {
+        let expected = format!(r#"<testcase name="{name}" classname="e2e-tests/src/lib.rs""#);
+        assert!(report.contains(&expected), "{report}");
+    }
+}
+
 #[test]
 fn benchmarking_everything() {
     let temp_dir = tempfile::TempDir::new().unwrap();
@@ -254,11 +280,61 @@ fn benchmarking_everything_with_mock_cachegrind() {
     for (name, expected_stats) in &EXPECTED_STATS.default {
         let actual_stats = outputs[name].stats.as_full().unwrap();
         assert_eq!(actual_stats, expected_stats);
+        assert!(outputs[name].wall_time.is_some());
     }
 
     test_handling_interrupts(&temp_dir);
 }
 
+#[test]
+fn collecting_benchmarks_first_with_mock_cachegrind() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let out_path = temp_dir.path().join("out.json");
+    let target_path = temp_dir.path().join("target");
+
+    // The exporter checks that the total number of benchmarks is reported before running them.
+    let output = Command::new(EXE_PATH)
+        .args(["--bench", "--collect-first"])
+        .env(EXPORTER_OUTPUT_VAR, &out_path)
+        .env("CACHEGRIND_WRAPPER", MOCK_CACHEGRIND_PATH)
+        .env("CACHEGRIND_OUT_DIR", &target_path)
+        .output()
+        .expect("failed running benches");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+
+    let outputs = read_outputs(&out_path);
+    for (name, expected_stats) in &EXPECTED_STATS.default {
+        let actual_stats = outputs[name].stats.as_full().unwrap();
+        assert_eq!(actual_stats, expected_stats);
+    }
+}
+
+#[test]
+fn benchmarking_command_with_mock_cachegrind() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let output = Command::new(COMMAND_BENCH_PATH)
+        .arg("--bench")
+        .current_dir(temp_dir.path())
+        .env("CACHEGRIND_WRAPPER", MOCK_CACHEGRIND_PATH)
+        // Relative to the working dir of the bench executable, which differs from the command one.
+        .env("CACHEGRIND_OUT_DIR", "target")
+        .output()
+        .expect("failed running benches");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+    assert!(stderr.contains("[√] command"), "{stderr}");
+    // Constant stats output by the mock `cachegrind` for commands
+    assert!(
+        stderr.contains("Instructions              1000"),
+        "{stderr}"
+    );
+
+    let out_path = temp_dir.path().join("target/command.cachegrind");
+    let out = fs::read_to_string(&out_path).unwrap();
+    assert!(out.contains("cmd: true"), "{out}");
+}
+
 fn test_handling_interrupts(temp_dir: &tempfile::TempDir) {
     let out_path = temp_dir.path().join("out.json");
     let target_path = temp_dir.path().join("target");
@@ -297,6 +373,8 @@ fn test_handling_interrupts(temp_dir: &tempfile::TempDir) {
     for (name, expected_stats) in &EXPECTED_STATS.default {
         let actual_stats = outputs[name].stats.as_full().unwrap();
         assert_eq!(actual_stats, expected_stats);
+        // Wall time isn't available for printed outputs.
+        assert!(outputs[name].wall_time.is_none());
     }
 }
 
@@ -321,6 +399,347 @@ fn benchmarking_with_mock_cachegrind_and_custom_profile() {
     assert_eq!(outputs["fib_short"].stats.total_instructions(), 1_739);
 }
 
+#[test]
+fn failing_on_zero_instructions_with_mock_cachegrind() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let target_path = temp_dir.path().join("target");
+    let mock_cachegrind = format!("{MOCK_CACHEGRIND_PATH}:--profile=empty");
+
+    let output = Command::new(EXE_PATH)
+        .args(["--bench", "fib_short"])
+        .env("CACHEGRIND_WRAPPER", &mock_cachegrind)
+        .env("CACHEGRIND_OUT_DIR", &target_path)
+        .output()
+        .expect("failed running benches");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+
+    let output = Command::new(EXE_PATH)
+        .args(["--bench", "--fail-on-zero", "fib_short"])
+        .env("CACHEGRIND_WRAPPER", &mock_cachegrind)
+        .env("CACHEGRIND_OUT_DIR", &target_path)
+        .output()
+        .expect("failed running benches");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!output.status.success(), "{stderr}");
+    assert!(stderr.contains("captured zero instructions"), "{stderr}");
+}
+
+#[test]
+fn running_approximate_benchmarks_with_mock_cachegrind() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let out_path = temp_dir.path().join("out.json");
+    let target_path = temp_dir.path().join("target");
+
+    let output = Command::new(EXE_PATH)
+        .args(["--bench", "--fast", "--exact", "fib_short"])
+        .env(EXPORTER_OUTPUT_VAR, &out_path)
+        .env("CACHEGRIND_WRAPPER", MOCK_CACHEGRIND_PATH)
+        .env("CACHEGRIND_OUT_DIR", &target_path)
+        .output()
+        .expect("failed running benches");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+    assert!(stderr.contains("[approximate]"), "{stderr}");
+
+    let outputs = read_outputs(&out_path);
+    let output = &outputs["fib_short"];
+    assert!(output.is_approximate);
+    assert!(output.prev_stats.is_none());
+    // The mock adds fixed overhead to the benchmark stats.
+    let expected_stats = &EXPECTED_STATS.default["fib_short"];
+    let stats = output.stats.as_full().unwrap();
+    assert!(
+        stats.instructions.total > expected_stats.instructions.total,
+        "{stats:?}"
+    );
+
+    // Outputs should not be persisted.
+    assert!(!target_path.join("fib_short.cachegrind").exists());
+    assert!(!target_path.join("fib_short.baseline.cachegrind").exists());
+}
+
+#[test]
+fn comparing_with_outputs_in_dir_with_mock_cachegrind() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let out_path = temp_dir.path().join("out.json");
+    let prev_target_path = temp_dir.path().join("prev-target");
+    let target_path = temp_dir.path().join("target");
+
+    let output = Command::new(EXE_PATH)
+        .args(["--bench", "fib_short"])
+        .env("CACHEGRIND_WRAPPER", MOCK_CACHEGRIND_PATH)
+        .env("CACHEGRIND_OUT_DIR", &prev_target_path)
+        .output()
+        .expect("failed running benches");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+
+    let mock_cachegrind = format!("{MOCK_CACHEGRIND_PATH}:--profile=comparison");
+    let output = Command::new(EXE_PATH)
+        .args(["--bench", "fib_short", "--vs-dir"])
+        .arg(&prev_target_path)
+        .env(EXPORTER_OUTPUT_VAR, &out_path)
+        .env("CACHEGRIND_WRAPPER", &mock_cachegrind)
+        .env("CACHEGRIND_OUT_DIR", &target_path)
+        .output()
+        .expect("failed running benches");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+
+    let outputs = read_outputs(&out_path);
+    let output = &outputs["fib_short"];
+    assert_eq!(output.stats.total_instructions(), 1_739);
+    let prev_stats = output.prev_stats.unwrap();
+    assert_eq!(
+        prev_stats.as_full().unwrap(),
+        &EXPECTED_STATS.default["fib_short"]
+    );
+}
+
+#[test]
+fn removing_cachegrind_outputs() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let out_path = temp_dir.path().join("out.json");
+    let target_path = temp_dir.path().join("target");
+
+    let output = Command::new(EXE_PATH)
+        .args(["--bench", "--keep-cachegrind-output=never", "fib"])
+        .env(EXPORTER_OUTPUT_VAR, &out_path)
+        .env("CACHEGRIND_WRAPPER", MOCK_CACHEGRIND_PATH)
+        .env("CACHEGRIND_OUT_DIR", &target_path)
+        .output()
+        .expect("failed running benches");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+
+    let outputs = read_outputs(&out_path);
+    assert!(outputs.contains_key("fib_short"), "{outputs:?}");
+    assert!(outputs["fib_short"].raw_output_path.is_none());
+    assert_eq!(count_files(&target_path), 0);
+}
+
+#[test]
+fn exposing_raw_output_paths() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let out_path = temp_dir.path().join("out.json");
+    let target_path = temp_dir.path().join("target");
+
+    let output = Command::new(EXE_PATH)
+        .args(["--bench", "fib_short"])
+        .env(EXPORTER_OUTPUT_VAR, &out_path)
+        .env("CACHEGRIND_WRAPPER", MOCK_CACHEGRIND_PATH)
+        .env("CACHEGRIND_OUT_DIR", &target_path)
+        .output()
+        .expect("failed running benches");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+
+    let outputs = read_outputs(&out_path);
+    let raw_output_path = outputs["fib_short"].raw_output_path.as_ref().unwrap();
+    assert_eq!(raw_output_path, &target_path.join("fib_short.cachegrind"));
+    assert!(raw_output_path.is_file());
+}
+
+#[test]
+fn disabling_cachegrind_output_backups() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let target_path = temp_dir.path().join("target");
+
+    for _ in 0..2 {
+        let output = Command::new(EXE_PATH)
+            .args(["--bench", "--no-backup", "fib"])
+            .env("CACHEGRIND_WRAPPER", MOCK_CACHEGRIND_PATH)
+            .env("CACHEGRIND_OUT_DIR", &target_path)
+            .output()
+            .expect("failed running benches");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(output.status.success(), "{stderr}");
+    }
+
+    assert!(count_files(&target_path) > 0);
+    assert_eq!(count_files_with_extension(&target_path, "old"), 0);
+}
+
+#[test]
+fn comparing_without_writing_outputs() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let out_path = temp_dir.path().join("out.json");
+    let target_path = temp_dir.path().join("target");
+
+    let output = Command::new(EXE_PATH)
+        .args(["--bench", "fib_short"])
+        .env("CACHEGRIND_WRAPPER", MOCK_CACHEGRIND_PATH)
+        .env("CACHEGRIND_OUT_DIR", &target_path)
+        .output()
+        .expect("failed running benches");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+    let file_count = count_files(&target_path);
+    assert!(file_count > 0);
+
+    let output = Command::new(EXE_PATH)
+        .args(["--bench", "--compare-only", "fib_short"])
+        .env(EXPORTER_OUTPUT_VAR, &out_path)
+        .env("CACHEGRIND_WRAPPER", MOCK_CACHEGRIND_PATH)
+        .env("CACHEGRIND_OUT_DIR", &target_path)
+        .output()
+        .expect("failed running benches");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+
+    let outputs = read_outputs(&out_path);
+    let output = &outputs["fib_short"];
+    assert!(output.prev_stats.is_some(), "{output:?}");
+    assert_eq!(count_files(&target_path), file_count);
+    assert_eq!(count_files_with_extension(&target_path, "old"), 0);
+}
+
+#[test]
+fn printing_commands_in_dry_run() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let target_path = temp_dir.path().join("target");
+
+    let output = Command::new(EXE_PATH)
+        .args(["--bench", "--dry-run", "fib/"])
+        .env("CACHEGRIND_WRAPPER", MOCK_CACHEGRIND_PATH)
+        .env("CACHEGRIND_OUT_DIR", &target_path)
+        .output()
+        .expect("failed running benches");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines.len(), 9, "{stdout}");
+    for line in &lines {
+        assert!(line.starts_with("fib/"), "{line}");
+        assert!(line.contains(MOCK_CACHEGRIND_PATH), "{line}");
+    }
+    let calibration_lines = lines
+        .iter()
+        .filter(|line| line.contains(" [calibration]: "));
+    for line in calibration_lines {
+        assert!(
+            line.contains("\"--cachegrind-instrument\" \"2\" \"+\""),
+            "{line}"
+        );
+    }
+    let baseline_lines = lines.iter().filter(|line| line.contains(" [baseline]: "));
+    assert_eq!(baseline_lines.count(), 3, "{stdout}");
+    let full_lines = lines.iter().filter(|line| line.contains(" [full]: "));
+    for line in full_lines {
+        assert!(line.contains("\" \"-\" \""), "{line}");
+    }
+    // Benchmarks should not be executed.
+    assert!(!target_path.exists());
+}
+
+#[test]
+fn dry_run_requires_bench_mode() {
+    let output = Command::new(EXE_PATH)
+        .args(["--dry-run", "fib/"])
+        .env("CACHEGRIND_WRAPPER", MOCK_CACHEGRIND_PATH)
+        .output()
+        .expect("failed running benches");
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("--bench"), "{stderr}");
+}
+
+#[test]
+fn failing_on_unmatched_exact_filter() {
+    let output = Command::new(EXE_PATH)
+        .args(["--bench", "--dry-run", "--exact", "fib/.*"])
+        .env("CACHEGRIND_WRAPPER", MOCK_CACHEGRIND_PATH)
+        .output()
+        .expect("failed running benches");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("No benchmarks match the exact filter `fib/.*`"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("consider dropping `--exact`"), "{stderr}");
+
+    // With `--allow-no-matches`, the hint is reported as a warning.
+    let output = Command::new(EXE_PATH)
+        .args([
+            "--bench",
+            "--dry-run",
+            "--allow-no-matches",
+            "--exact",
+            "fib/.*",
+        ])
+        .env("CACHEGRIND_WRAPPER", MOCK_CACHEGRIND_PATH)
+        .output()
+        .expect("failed running benches");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+    assert!(stderr.contains("consider dropping `--exact`"), "{stderr}");
+
+    let output = Command::new(EXE_PATH)
+        .args(["--bench", "--dry-run", "--exact", "fib/15"])
+        .env("CACHEGRIND_WRAPPER", MOCK_CACHEGRIND_PATH)
+        .output()
+        .expect("failed running benches");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+}
+
+#[test]
+fn failing_on_unmatched_filter() {
+    let output = Command::new(EXE_PATH)
+        .args(["--bench", "--dry-run", "^fibb"])
+        .env("CACHEGRIND_WRAPPER", MOCK_CACHEGRIND_PATH)
+        .output()
+        .expect("failed running benches");
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("No benchmarks match the filter `^fibb`"),
+        "{stderr}"
+    );
+
+    let output = Command::new(EXE_PATH)
+        .args(["--bench", "--dry-run", "--allow-no-matches", "^fibb"])
+        .env("CACHEGRIND_WRAPPER", MOCK_CACHEGRIND_PATH)
+        .output()
+        .expect("failed running benches");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+    assert!(output.stdout.is_empty());
+}
+
+fn count_files(dir: &Path) -> usize {
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                count_files(&entry.path())
+            } else {
+                1
+            }
+        })
+        .sum()
+}
+
+fn count_files_with_extension(dir: &Path, extension: &str) -> usize {
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                count_files_with_extension(&path, extension)
+            } else {
+                usize::from(path.extension().is_some_and(|ext| ext == extension))
+            }
+        })
+        .sum()
+}
+
 #[test]
 fn handling_panics_in_benches() {
     let temp_dir = tempfile::TempDir::new().unwrap();
@@ -343,6 +762,64 @@ fn handling_panics_in_benches() {
     assert!(stderr.contains("emulated panic!"), "{stderr}");
 }
 
+#[test]
+fn failing_fast_on_bench_errors() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let target_path = temp_dir.path().join("target");
+
+    let mock_cachegrind = format!("{MOCK_CACHEGRIND_PATH}:--emulate-panic");
+    for fail_fast in [false, true] {
+        let mut command = Command::new(EXE_PATH);
+        command
+            .args(["--bench", "--jobs=1"])
+            .env("CACHEGRIND_WRAPPER", &mock_cachegrind)
+            .env("CACHEGRIND_OUT_DIR", &target_path);
+        if fail_fast {
+            command.arg("--fail-fast");
+        }
+        let output = command.output().expect("failed running benches");
+
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert_eq!(output.status.code(), Some(1), "{stderr}");
+        let summary = stderr
+            .lines()
+            .find(|line| line.contains("There were benchmark failures: "))
+            .unwrap_or_else(|| panic!("no failures summary: {stderr}"));
+        let error_count = stderr.matches("cachegrind exited abnormally").count();
+        if fail_fast {
+            assert_eq!(error_count, 1, "{stderr}");
+            assert_eq!(summary.matches('`').count(), 2, "{summary}");
+        } else {
+            assert!(error_count >= EXPECTED_BENCH_NAMES.len(), "{stderr}");
+            // All failed benchmarks should be listed in the summary.
+            for name in EXPECTED_BENCH_NAMES {
+                assert!(summary.contains(&format!("`{name}`")), "{summary}");
+            }
+        }
+    }
+}
+
+#[test]
+fn showing_output_of_spawned_processes() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let target_path = temp_dir.path().join("target");
+
+    let mock_cachegrind = format!("{MOCK_CACHEGRIND_PATH}:--emulate-panic");
+    let output = Command::new(EXE_PATH)
+        .args(["--bench", "--jobs=1", "--fail-fast", "--show-output"])
+        .env("CACHEGRIND_WRAPPER", &mock_cachegrind)
+        .env("CACHEGRIND_OUT_DIR", &target_path)
+        .output()
+        .expect("failed running benches");
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!output.status.success(), "{stderr}");
+    // The panic message should be output directly rather than included into the error.
+    assert!(stderr.contains("emulated panic!"), "{stderr}");
+    assert!(!stderr.contains("---- cachegrind stderr ----"), "{stderr}");
+    assert!(stderr.contains("cachegrind exited abnormally"), "{stderr}");
+}
+
 #[test]
 fn printing_benchmark_results() {
     let temp_dir = tempfile::TempDir::new().unwrap();
@@ -371,7 +848,9 @@ fn printing_benchmark_results() {
         .lines()
         .filter(|line| line.contains("no data for benchmark"))
         .count();
-    assert_eq!(benchmarks_without_data, 7); // `fib/`, `guard` and `random_walk/` benches
+    // `fib/{15,20,25}`, `guard`, `guard/explicit`, `guard/drop`, `black_box_drop` and
+    // `random_walk/{1000000,10000000}` benches
+    assert_eq!(benchmarks_without_data, 9);
 
     // Check that only outputs for benches that have already been run are supplied to the processor.
     let outputs = read_outputs(&out_path);
@@ -478,6 +957,19 @@ fn disabling_cache_simulation() {
         guard_instructions.abs_diff(short_instructions) < 10,
         "short={short_instructions}, guard={guard_instructions}"
     );
+    // Only dropping the guard should be captured, which is equivalent to `fib/20`.
+    let drop_guard_instructions = outputs["guard/drop"].stats.total_instructions();
+    let fib_instructions = outputs["fib/20"].stats.total_instructions();
+    assert!(
+        drop_guard_instructions.abs_diff(fib_instructions) < 100,
+        "fib/20={fib_instructions}, guard/drop={drop_guard_instructions}"
+    );
+    // The value is unused, but its computation should still be captured.
+    let drop_instructions = outputs["black_box_drop"].stats.total_instructions();
+    assert!(
+        drop_instructions.abs_diff(short_instructions) < 10,
+        "short={short_instructions}, black_box_drop={drop_instructions}"
+    );
 
     if !cfg!(debug_assertions) {
         for (name, expected_stats) in &EXPECTED_STATS.default {